@@ -0,0 +1,165 @@
+use crate::{DateStyle, fmt_time};
+
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use poise::serenity_prelude::{ChannelId, UserId};
+
+/// How long to wait before reminding the operators of a problem that has not cleared.
+const REALERT_AFTER: TimeDelta = TimeDelta::hours(6);
+
+/// Where alerts about the bot's own problems are delivered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertDestination {
+    /// A channel the operators watch.
+    Channel(ChannelId),
+    /// A direct message to an operator.
+    User(UserId),
+}
+
+/// Something that keeps going wrong, which the operators should know about.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Problem {
+    /// Fetching the model with the given name from the worldstate.
+    Fetch(String),
+    /// Delivering messages to the target with the given key.
+    Send(String),
+    /// Saving the given cache to disk.
+    Dump(String),
+    /// Recognising the Archon hunted this week.
+    UnknownArchon,
+}
+
+impl Problem {
+    /// How many times in a row the problem must happen before it is alerted. Fetches and sends
+    /// fail now and again, and are retried, so only repeated failures are worth an alert.
+    fn threshold(&self) -> u32 {
+        match self {
+            Problem::Fetch(_) => 3,
+            Problem::Send(_) => 2,
+            Problem::Dump(_) | Problem::UnknownArchon => 1,
+        }
+    }
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::Fetch(queryable) => write!(f, "fetching {queryable} from the worldstate"),
+            Problem::Send(target) => write!(f, "delivering messages to {target}"),
+            Problem::Dump(cache) => write!(f, "saving the {cache}"),
+            Problem::UnknownArchon => write!(f, "recognising this week's Archon"),
+        }
+    }
+}
+
+/// A problem that has happened and not yet cleared.
+#[derive(Debug, Clone)]
+struct Ongoing {
+    since: DateTime<Utc>,
+    occurrences: u32,
+    /// When the operators were last alerted, if they have been.
+    alerted: Option<DateTime<Utc>>,
+}
+
+/// Tracks the bot's ongoing problems, deciding when to alert the operators about them and when to
+/// tell them a problem has cleared.
+#[derive(Debug, Clone, Default)]
+pub struct Alerts {
+    ongoing: HashMap<Problem, Ongoing>,
+}
+
+impl Alerts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record that a problem happened. Returns the alert to send, if the problem has now happened
+    /// often enough and the operators have not been alerted about it recently.
+    pub fn happened(
+        &mut self,
+        problem: Problem,
+        error: &str,
+        now: DateTime<Utc>,
+    ) -> Option<String> {
+        let threshold = problem.threshold();
+        let ongoing = self.ongoing.entry(problem.clone()).or_insert(Ongoing {
+            since: now,
+            occurrences: 0,
+            alerted: None,
+        });
+        ongoing.occurrences += 1;
+
+        let due = match ongoing.alerted {
+            Some(alerted) => now - alerted >= REALERT_AFTER,
+            None => ongoing.occurrences >= threshold,
+        };
+        if !due {
+            return None;
+        }
+        ongoing.alerted = Some(now);
+
+        let times = match ongoing.occurrences {
+            1 => "once".to_string(),
+            n => format!("{n} times"),
+        };
+        Some(format!(
+            "**Problem:** {problem} has failed {times} since {}.\n```\n{error}\n```",
+            fmt_time(&ongoing.since, DateStyle::Discord)
+        ))
+    }
+
+    /// Record that a problem did not happen. Returns a recovery notice to send, if the operators
+    /// were alerted about it.
+    pub fn cleared(&mut self, problem: &Problem) -> Option<String> {
+        let ongoing = self.ongoing.remove(problem)?;
+        ongoing.alerted?;
+
+        Some(format!(
+            "**Recovered:** {problem} works again. It had failed since {}.",
+            fmt_time(&ongoing.since, DateStyle::Discord)
+        ))
+    }
+}
+
+#[cfg(test)]
+mod alerts_test {
+    use super::*;
+
+    #[test]
+    fn alert_after_threshold() {
+        let mut alerts = Alerts::new();
+        let problem = Problem::Fetch("News".to_string());
+        let fail = |alerts: &mut Alerts, at| alerts.happened(problem.clone(), "timed out", at);
+        let now: DateTime<Utc> = "2025-07-21T00:00:00Z".parse().unwrap();
+
+        assert!(fail(&mut alerts, now).is_none());
+        assert!(fail(&mut alerts, now).is_none());
+        let alert = fail(&mut alerts, now).unwrap();
+        assert!(alert.contains("fetching News from the worldstate has failed 3 times"));
+
+        // The operators are reminded only once the problem has gone on for a while.
+        assert!(fail(&mut alerts, now + TimeDelta::hours(1)).is_none());
+        assert!(fail(&mut alerts, now + REALERT_AFTER).is_some());
+
+        assert!(alerts.cleared(&problem).unwrap().contains("works again"));
+        assert!(alerts.cleared(&problem).is_none());
+    }
+
+    #[test]
+    fn clear_without_alert() {
+        let mut alerts = Alerts::new();
+        let problem = Problem::Send("channel-1".to_string());
+        let now = Utc::now();
+
+        assert!(alerts.happened(problem.clone(), "denied", now).is_none());
+        assert!(alerts.cleared(&problem).is_none());
+
+        // Counting starts again once a problem has cleared.
+        assert!(alerts.happened(problem.clone(), "denied", now).is_none());
+
+        let alert = alerts.happened(Problem::UnknownArchon, "Archon Kahl", now);
+        assert!(alert.unwrap().contains("has failed once"));
+    }
+}
@@ -0,0 +1,141 @@
+use crate::{DateStyle, fmt_api_date, fmt_time};
+
+use std::fmt::{self, Display, Formatter};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use poise::ChoiceParameter;
+use warframe::worldstate::TimedEvent;
+use warframe::worldstate::queryable::ArchonHunt;
+
+/// The Archons hunted in the weekly Archon Hunt, which take turns in this order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Archon {
+    Amar,
+    Nira,
+    Boreal,
+}
+
+/// The colours of Archon Shard dropped by the Archon Hunt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ChoiceParameter)]
+pub enum Shard {
+    Crimson,
+    Amber,
+    Azure,
+}
+
+impl Archon {
+    /// Find the Archon from the boss of an Archon Hunt, e.g., "Archon Amar".
+    pub fn from_boss(boss: &str) -> Option<Self> {
+        match boss.to_lowercase() {
+            s if s.contains("amar") => Some(Archon::Amar),
+            s if s.contains("nira") => Some(Archon::Nira),
+            s if s.contains("boreal") => Some(Archon::Boreal),
+            _ => None,
+        }
+    }
+
+    /// The Archon hunted the week after this one.
+    pub fn next(self) -> Self {
+        match self {
+            Archon::Amar => Archon::Nira,
+            Archon::Nira => Archon::Boreal,
+            Archon::Boreal => Archon::Amar,
+        }
+    }
+
+    /// The colour of shard this Archon drops.
+    pub fn shard(self) -> Shard {
+        match self {
+            Archon::Amar => Shard::Crimson,
+            Archon::Nira => Shard::Amber,
+            Archon::Boreal => Shard::Azure,
+        }
+    }
+}
+
+impl Display for Archon {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Archon::Amar => write!(f, "Archon Amar"),
+            Archon::Nira => write!(f, "Archon Nira"),
+            Archon::Boreal => write!(f, "Archon Boreal"),
+        }
+    }
+}
+
+impl Display for Shard {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} Archon Shard", self.name())
+    }
+}
+
+/// The Archons hunted in the weeks after the given one, with the start of each week.
+pub fn forecast(
+    current: Archon,
+    start: DateTime<Utc>,
+    weeks_ahead: u32,
+) -> Vec<(DateTime<Utc>, Archon)> {
+    (1..=weeks_ahead.into())
+        .scan(current, |archon, week| {
+            *archon = archon.next();
+            Some((start + TimeDelta::weeks(week), *archon))
+        })
+        .collect()
+}
+
+/// Describe the current Archon Hunt, its missions and how long remains, followed by the Archons
+/// hunted in the given number of weeks after it.
+pub fn archon_message(hunt: &ArchonHunt, weeks_ahead: u32) -> String {
+    let Some(archon) = Archon::from_boss(&hunt.boss) else {
+        return format!("Unknown Archon Boss: {}", hunt.boss);
+    };
+
+    let mut lines = vec![format!(
+        "**{archon}** drops the {} until {}.",
+        archon.shard(),
+        fmt_time(&hunt.expiry(), DateStyle::Discord)
+    )];
+    lines.extend(
+        hunt.missions
+            .iter()
+            .map(|mission| format!("- {} on {}", mission.r#type, mission.node)),
+    );
+
+    if weeks_ahead > 0 {
+        lines.push("**Coming up**".to_string());
+        for (start, archon) in forecast(archon, hunt.activation(), weeks_ahead) {
+            lines.push(format!(
+                "- {}: {archon}, {}",
+                fmt_api_date(&start, DateStyle::Discord),
+                archon.shard()
+            ));
+        }
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod archon_test {
+    use super::*;
+
+    #[test]
+    fn from_boss() {
+        assert_eq!(Archon::from_boss("Archon Amar"), Some(Archon::Amar));
+        assert_eq!(Archon::from_boss("archon boreal"), Some(Archon::Boreal));
+        assert_eq!(Archon::from_boss("Lotus"), None);
+    }
+
+    #[test]
+    fn forecast_cycles() {
+        let start = "2025-09-15T00:00:00Z".parse().unwrap();
+        let weeks = forecast(Archon::Nira, start, 3);
+
+        assert_eq!(
+            weeks.iter().map(|(_, archon)| *archon).collect::<Vec<_>>(),
+            [Archon::Boreal, Archon::Amar, Archon::Nira]
+        );
+        assert_eq!(weeks[0].0, start + TimeDelta::weeks(1));
+        assert_eq!(weeks[1].1.shard().to_string(), "Crimson Archon Shard");
+    }
+}
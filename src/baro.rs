@@ -0,0 +1,250 @@
+use crate::cache::{read_dump, write_dump};
+use crate::{DateStyle, fmt_time, warning};
+
+use anyhow::Result;
+use chrono::{DateTime, TimeDelta, Utc};
+use poise::serenity_prelude::MessageId;
+use serde::{Deserialize, Serialize};
+use warframe::worldstate::TimedEvent;
+use warframe::worldstate::queryable::VoidTrader;
+
+/// A visit from Baro Ki'Teer whose inventory was posted, so that the posts can be updated once he
+/// leaves.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BaroVisit {
+    pub location: String,
+    pub expiry: DateTime<Utc>,
+    /// The messages the inventory was posted as, in the order they were delivered.
+    pub messages: Vec<PostedMessage>,
+    /// Whether the reminder that he is leaving soon has been sent.
+    pub reminded: bool,
+    /// Whether the posts have been updated to say that he has left.
+    pub departed: bool,
+}
+
+/// A message delivered to a target.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PostedMessage {
+    /// The key of the target the message was delivered to.
+    pub target: String,
+    #[serde(with = "crate::discord_id")]
+    pub id: MessageId,
+}
+
+impl BaroVisit {
+    pub fn new(trader: &VoidTrader) -> Self {
+        Self {
+            location: trader.location.clone(),
+            expiry: trader.expiry(),
+            messages: vec![],
+            reminded: false,
+            departed: false,
+        }
+    }
+
+    /// Load the last visit from its default location, if there is one.
+    pub fn load() -> Option<Self> {
+        read_dump("baro_visit.bin")
+            .inspect_err(|e| warning!(context = "loading baro visit", "{e}"))
+            .ok()
+            .flatten()
+    }
+
+    /// Returns `true` if a reminder sent the given time before he leaves is due.
+    pub fn reminder_due(&self, now: DateTime<Utc>, before: TimeDelta) -> bool {
+        !self.reminded && now >= self.expiry - before && now < self.expiry
+    }
+
+    /// Returns `true` if he has left, but the posts have not been updated yet.
+    pub fn has_left(&self, now: DateTime<Utc>) -> bool {
+        !self.departed && now >= self.expiry
+    }
+
+    /// The reminder that he is leaving soon.
+    pub fn reminder_message(&self) -> String {
+        format!(
+            "Reminder: Baro Ki'Teer leaves {} on {}.",
+            self.location,
+            fmt_time(&self.expiry, DateStyle::Discord)
+        )
+    }
+
+    /// The message that replaces the first post to each target once he has left, given his next
+    /// visit.
+    pub fn departure_message(&self, next: &VoidTrader) -> String {
+        format!(
+            "Baro Ki'Teer has left {}. He will be at {} on {}.",
+            self.location,
+            next.location,
+            fmt_time(&next.activation(), DateStyle::Discord),
+        )
+    }
+
+    /// Returns the messages to edit once he has left, with their new content. The first post to
+    /// each target is replaced with the given departure message.
+    pub fn departure_edits(&self, departure: &str) -> Vec<(PostedMessage, String)> {
+        let mut seen_targets = vec![];
+
+        self.messages
+            .iter()
+            .map(|message| {
+                // Only the overview is replaced with the details of his next visit. The inventory
+                // tables that follow it are simply cleared.
+                let content = match seen_targets.contains(&message.target) {
+                    false => departure.to_string(),
+                    true => "Baro Ki'Teer's inventory is no longer available.".to_string(),
+                };
+                seen_targets.push(message.target.clone());
+
+                (message.clone(), content)
+            })
+            .collect()
+    }
+
+    /// Dump the visit to the default location.
+    pub fn dump(&self) -> Result<()> {
+        write_dump("baro_visit.bin", self)
+    }
+}
+
+/// A reminder of Baro Ki'Teer's arrival, identified by the time he arrives and how many hours
+/// before then it is sent.
+pub type ArrivalReminder = (DateTime<Utc>, u32);
+
+/// Returns which of the reminders, given in hours before Baro Ki'Teer arrives, is due at the
+/// given time. Only the latest reminder that has been reached is due, so a bot that was not
+/// running earlier does not send several at once.
+pub fn arrival_reminder_due(
+    activation: DateTime<Utc>,
+    now: DateTime<Utc>,
+    reminders: &[u32],
+) -> Option<u32> {
+    if now >= activation {
+        return None;
+    }
+
+    reminders
+        .iter()
+        .copied()
+        .filter(|&hours| now >= activation - TimeDelta::hours(hours.into()))
+        .min()
+}
+
+/// The reminder that Baro Ki'Teer is arriving at the given relay. The time left is shown by
+/// Discord, as a reminder may be sent later than configured.
+pub fn arrival_message(location: &str, activation: DateTime<Utc>) -> String {
+    format!(
+        "Baro Ki'Teer arrives at {location} on {}.",
+        fmt_time(&activation, DateStyle::Discord),
+    )
+}
+
+#[cfg(test)]
+mod baro_test {
+    use super::*;
+
+    fn visit(expiry: DateTime<Utc>) -> BaroVisit {
+        BaroVisit {
+            location: "Strata Relay (Earth)".into(),
+            expiry,
+            messages: vec![],
+            reminded: false,
+            departed: false,
+        }
+    }
+
+    #[test]
+    fn reminder_due() {
+        let now = Utc::now();
+        let before = TimeDelta::hours(2);
+
+        assert!(!visit(now + TimeDelta::hours(3)).reminder_due(now, before));
+        assert!(visit(now + TimeDelta::hours(1)).reminder_due(now, before));
+        assert!(!visit(now - TimeDelta::hours(1)).reminder_due(now, before));
+
+        let mut reminded = visit(now + TimeDelta::hours(1));
+        reminded.reminded = true;
+        assert!(!reminded.reminder_due(now, before));
+    }
+
+    #[test]
+    fn arrival_reminder() {
+        let now = Utc::now();
+        let reminders = [24, 1];
+
+        let arrival = |in_hours| now + TimeDelta::hours(in_hours);
+
+        assert_eq!(arrival_reminder_due(arrival(30), now, &reminders), None);
+        assert_eq!(arrival_reminder_due(arrival(23), now, &reminders), Some(24));
+        assert_eq!(arrival_reminder_due(arrival(1), now, &reminders), Some(1));
+        assert_eq!(arrival_reminder_due(arrival(0), now, &reminders), None);
+        assert_eq!(arrival_reminder_due(arrival(1), now, &[]), None);
+    }
+
+    #[test]
+    fn arrival_message_late_start() {
+        // Started three hours before he arrives, when the first reminder is still due.
+        let now = Utc::now();
+        let activation = now + TimeDelta::hours(3);
+        assert_eq!(arrival_reminder_due(activation, now, &[24, 1]), Some(24));
+
+        let message = arrival_message("Strata Relay (Earth)", activation);
+        assert!(message.contains(&format!("<t:{}:R>", activation.timestamp())));
+        assert!(!message.contains("hours"));
+    }
+
+    #[test]
+    fn has_left() {
+        let now = Utc::now();
+
+        assert!(!visit(now + TimeDelta::minutes(1)).has_left(now));
+        assert!(visit(now).has_left(now));
+
+        let mut departed = visit(now);
+        departed.departed = true;
+        assert!(!departed.has_left(now));
+    }
+
+    #[test]
+    fn departure_edits() {
+        let mut visit = visit(Utc::now());
+        for (target, id) in [("a", 1), ("a", 2), ("b", 3)] {
+            visit.messages.push(PostedMessage {
+                target: target.into(),
+                id: MessageId::new(id),
+            });
+        }
+
+        let edits = visit.departure_edits("He has left.");
+        let contents = edits
+            .iter()
+            .map(|(message, content)| (message.id.get(), content.as_str()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            contents,
+            [
+                (1, "He has left."),
+                (2, "Baro Ki'Teer's inventory is no longer available."),
+                (3, "He has left."),
+            ]
+        );
+    }
+
+    #[test]
+    fn serialize_is_deserialize() {
+        use bincode::serde::{decode_from_slice, encode_to_vec};
+
+        let cfg = bincode::config::standard();
+
+        let mut visit = visit(DateTime::UNIX_EPOCH);
+        visit.messages.push(PostedMessage {
+            target: "channel-1".into(),
+            id: MessageId::new(1234),
+        });
+        let serialized = encode_to_vec(&visit, cfg).unwrap();
+        let (deserialized, _): (BaroVisit, _) = decode_from_slice(&serialized, cfg).unwrap();
+
+        assert_eq!(visit, deserialized);
+    }
+}
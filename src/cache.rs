@@ -1,3 +1,4 @@
+use std::borrow::Borrow;
 use std::collections::{HashSet, VecDeque};
 use std::fs::{self, File};
 use std::hash::Hash;
@@ -10,6 +11,42 @@ use dirs::cache_dir;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
+/// Get the path to a file in the bot's cache directory, ensuring the directory's existence.
+pub fn cache_file(file_name: &str) -> Option<PathBuf> {
+    let path = cache_dir()?.join("wf_bot");
+    fs::create_dir_all(&path).ok()?;
+
+    Some(path.join(file_name))
+}
+
+/// Load a binary dump of a value from a file in the bot's cache directory. `None` if the value has
+/// never been dumped.
+pub fn read_dump<T: DeserializeOwned>(file_name: &str) -> Result<Option<T>> {
+    let path =
+        cache_file(file_name).ok_or_else(|| anyhow!("could not get path for {file_name}"))?;
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let mut file_handle = File::open(path)?;
+    let cfg = bincode::config::standard();
+
+    Ok(Some(decode_from_std_read(&mut file_handle, cfg)?))
+}
+
+/// Dump a value to a file in the bot's cache directory.
+pub fn write_dump<T: Serialize>(file_name: &str, value: &T) -> Result<()> {
+    let path = cache_file(file_name)
+        .ok_or_else(|| anyhow!("could not get path for {file_name}, skipping dump"))?;
+    let file_handle = File::create(path)?;
+    let mut writer = BufWriter::new(file_handle);
+
+    let cfg = bincode::config::standard();
+    encode_into_std_write(value, &mut writer, cfg)?;
+
+    Ok(())
+}
+
 pub trait Cacheable: Eq + Hash + Clone {}
 impl<T: Eq + Hash + Clone> Cacheable for T {}
 
@@ -41,37 +78,63 @@ where
             return false;
         }
 
-        if self.queue.len() == CACHE_SIZE {
-            if let Some(oldest) = self.queue.pop_front() {
-                self.set.remove(&oldest);
-            }
+        if self.queue.len() == CACHE_SIZE
+            && let Some(oldest) = self.queue.pop_front()
+        {
+            self.set.remove(&oldest);
         }
         self.set.insert(value.clone());
         self.queue.push_back(value);
         true
     }
 
-    #[allow(unused)]
     /// Returns the length of the cache.
     pub fn len(&self) -> usize {
         self.queue.len()
     }
 
+    /// Returns `true` if the cache contains no values.
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
     /// Returns `true` if the cache contains the value
-    pub fn contains(&self, value: &T) -> bool {
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
         self.set.contains(value)
     }
 
-    /// Updates the cache with a list of items, ignoring previously seen values and caching new
-    /// ones.
+    /// Replaces a cached value with an equal one, keeping its place in the cache.
+    ///
+    /// Returns `false`, and does not insert the value, if no equal value was cached.
+    pub fn replace(&mut self, value: T) -> bool {
+        let Some(cached) = self.queue.iter_mut().find(|cached| **cached == value) else {
+            return false;
+        };
+
+        *cached = value.clone();
+        self.set.replace(value);
+        true
+    }
+
+    /// Returns an iterator over the cached values, from oldest to newest.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.queue.iter()
+    }
+
+    /// Marks every item as seen except for the last `keep`, which are left to be reported.
     ///
-    /// Returns the list of newly added item. That is, unseen items.
-    pub fn difference(&mut self, values: &[T]) -> Vec<T> {
-        values
-            .iter()
-            .filter(|&value| self.insert(value.clone()))
-            .cloned()
-            .collect()
+    /// Returns the number of items marked as seen.
+    pub fn seed(&mut self, values: &[T], keep: usize) -> usize {
+        let seeded = values.len().saturating_sub(keep);
+        values[..seeded].iter().cloned().for_each(|value| {
+            self.insert(value);
+        });
+
+        seeded
     }
 }
 
@@ -84,17 +147,24 @@ where
         Self::from_cache(cache_name).unwrap_or(Self::default())
     }
 
-    /// Get the path to the cache directory.
-    fn cache_path(cache_name: &str) -> Option<PathBuf> {
-        // Construct the path to the cache directory, ensuring its existence.
-        let path = cache_dir()?.join("wf_bot");
-        fs::create_dir_all(&path).ok()?;
+    /// Load the cache from its default location, if it has ever been dumped there.
+    pub fn existing(cache_name: &str) -> Option<Self> {
+        let path = cache_file(&format!("cache_{cache_name}.bin"))?;
 
-        // Construct the path to the cache file, ensuring its existence as well.
-        let full_path = path.join(format!("cache_{cache_name}.bin"));
+        path.is_file()
+            .then(|| Self::from_cache(cache_name))
+            .flatten()
+    }
+
+    /// Get the path to the cache file.
+    fn cache_path(cache_name: &str) -> Option<PathBuf> {
+        // Construct the path to the cache file, ensuring its existence.
+        let full_path = cache_file(&format!("cache_{cache_name}.bin"))?;
         if !full_path.is_file() {
-            let mut empty = Self::default();
-            empty.cache_name = cache_name.to_string();
+            let empty = Self {
+                cache_name: cache_name.to_string(),
+                ..Self::default()
+            };
 
             let file_handle = File::create(&full_path).ok()?;
             let mut writer = BufWriter::new(file_handle);
@@ -224,6 +294,48 @@ mod cache_test {
         assert!(!cache.contains(&47));
     }
 
+    #[test]
+    fn seed() {
+        let mut cache: SeenCache<usize, 5> = SeenCache::default();
+
+        assert_eq!(cache.seed(&[1, 2, 3, 4], 1), 3);
+        assert!(cache.contains(&3));
+        assert!(!cache.contains(&4));
+
+        let mut cache: SeenCache<usize, 5> = SeenCache::default();
+        assert_eq!(cache.seed(&[1, 2], 3), 0);
+        assert!(cache.is_empty());
+    }
+
+    /// A value that is equal to another when their keys are equal.
+    #[derive(Debug, Clone)]
+    struct Keyed(u8, char);
+
+    impl PartialEq for Keyed {
+        fn eq(&self, other: &Self) -> bool {
+            self.0 == other.0
+        }
+    }
+
+    impl Eq for Keyed {}
+
+    impl Hash for Keyed {
+        fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+            self.0.hash(state);
+        }
+    }
+
+    #[test]
+    fn replace() {
+        let mut cache: SeenCache<Keyed, 3> = SeenCache::from(&[Keyed(1, 'a'), Keyed(2, 'b')][..]);
+
+        assert!(!cache.replace(Keyed(3, 'c')));
+        assert!(!cache.contains(&Keyed(3, 'c')));
+
+        assert!(cache.replace(Keyed(1, 'z')));
+        assert_eq!(cache.iter().next().unwrap().1, 'z');
+    }
+
     #[test]
     fn insert() {
         let mut cache: SeenCache<usize, 3> = SeenCache::default();
@@ -0,0 +1,237 @@
+use crate::archon::Archon;
+use crate::circuit::{circuit_at, sp_circuit_at, week_start};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use warframe::worldstate::TimedEvent;
+use warframe::worldstate::queryable::{ArchonHunt, VoidTrader};
+
+/// How many weeks ahead the calendar covers.
+const WEEKS_AHEAD: i64 = 8;
+
+/// How many of Baro Ki'Teer's visits the calendar covers, including his current or next one.
+const BARO_VISITS: i64 = 4;
+
+/// How often Baro Ki'Teer visits.
+const BARO_INTERVAL: TimeDelta = TimeDelta::weeks(2);
+
+/// An event in the calendar.
+#[derive(Debug, Clone, PartialEq)]
+struct CalendarEvent {
+    /// Unique within the calendar, and the same each time the event is generated.
+    uid: String,
+    summary: String,
+    description: String,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+}
+
+impl CalendarEvent {
+    fn write(&self, lines: &mut Vec<String>, now: DateTime<Utc>) {
+        lines.extend([
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}@wf-bot", self.uid),
+            format!("DTSTAMP:{}", ics_time(&now)),
+            format!("DTSTART:{}", ics_time(&self.start)),
+            format!("DTEND:{}", ics_time(&self.end)),
+            format!("SUMMARY:{}", escape(&self.summary)),
+            format!("DESCRIPTION:{}", escape(&self.description)),
+            "END:VEVENT".to_string(),
+        ]);
+    }
+}
+
+/// Create an iCalendar of the weekly resets, The Circuit's rotation and the Archon Hunts for the
+/// coming weeks, along with Baro Ki'Teer's next visits. His visits after the one the worldstate
+/// reports are predicted, as are the Archons after this week's.
+pub fn calendar(
+    now: DateTime<Utc>,
+    trader: Option<&VoidTrader>,
+    archon_hunt: Option<&ArchonHunt>,
+) -> String {
+    let mut events = weekly_events(
+        now,
+        archon_hunt.and_then(|hunt| Archon::from_boss(&hunt.boss)),
+    );
+    if let Some(trader) = trader {
+        events.extend(baro_events(trader));
+    }
+
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//wf-bot//Warframe timers//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "X-WR-CALNAME:Warframe".to_string(),
+    ];
+    for event in events {
+        event.write(&mut lines, now);
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    lines
+        .iter()
+        .map(|line| fold(line))
+        .map(|line| line + "\r\n")
+        .collect()
+}
+
+/// The weekly resets, The Circuit's offerings and the Archon Hunts, starting this week.
+fn weekly_events(now: DateTime<Utc>, archon: Option<Archon>) -> Vec<CalendarEvent> {
+    let this_week = week_start(now);
+    let mut events = vec![];
+
+    let mut archon = archon;
+    for week in 0..WEEKS_AHEAD {
+        let start = this_week + TimeDelta::weeks(week);
+        let end = start + TimeDelta::weeks(1);
+        let date = start.format("%Y%m%d");
+
+        events.push(CalendarEvent {
+            uid: format!("circuit-{date}"),
+            summary: format!("The Circuit: {}", circuit_at(start).join(", ")),
+            description: format!(
+                "The Circuit: {}\nThe Circuit (Steel Path): {}",
+                circuit_at(start).join(", "),
+                sp_circuit_at(start).join(", ")
+            ),
+            start,
+            end,
+        });
+
+        if let Some(current) = archon {
+            events.push(CalendarEvent {
+                uid: format!("archon-{date}"),
+                summary: format!("{current}: {}", current.shard()),
+                description: format!("The Archon Hunt drops the {}.", current.shard()),
+                start,
+                end,
+            });
+            archon = Some(current.next());
+        }
+
+        // This week's reset has already happened.
+        if week > 0 {
+            events.push(CalendarEvent {
+                uid: format!("weekly-reset-{date}"),
+                summary: "Weekly reset".to_string(),
+                description: "The Circuit and the Archon Hunt reset.".to_string(),
+                start,
+                end: start + TimeDelta::hours(1),
+            });
+        }
+    }
+
+    events
+}
+
+/// Baro Ki'Teer's current or next visit, followed by his predicted visits after it.
+fn baro_events(trader: &VoidTrader) -> Vec<CalendarEvent> {
+    (0..BARO_VISITS)
+        .map(|visit| {
+            let start = trader.activation() + BARO_INTERVAL * visit as i32;
+            let (summary, description) = match visit {
+                0 => (
+                    format!("Baro Ki'Teer at {}", trader.location),
+                    format!("Baro Ki'Teer visits {}.", trader.location),
+                ),
+                _ => (
+                    "Baro Ki'Teer (predicted)".to_string(),
+                    "Baro Ki'Teer is predicted to visit. His location is announced closer to the \
+                     time."
+                        .to_string(),
+                ),
+            };
+
+            CalendarEvent {
+                uid: format!("baro-{}", start.format("%Y%m%d")),
+                summary,
+                description,
+                start,
+                end: start + (trader.expiry() - trader.activation()),
+            }
+        })
+        .collect()
+}
+
+/// Format a time in UTC as iCalendar expects.
+fn ics_time(time: &DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Escape the characters iCalendar gives meaning to in text values.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Fold a content line so that no line is longer than 75 bytes, as iCalendar requires.
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut length = 0;
+
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            // The leading space counts towards the length of the continuation line.
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+
+    folded
+}
+
+#[cfg(test)]
+mod calendar_test {
+    use super::*;
+
+    #[test]
+    fn escape_text() {
+        assert_eq!(
+            escape("Ack & Brunt, Soma; a\\b\nc"),
+            "Ack & Brunt\\, Soma\\; a\\\\b\\nc"
+        );
+    }
+
+    #[test]
+    fn fold_long_lines() {
+        let line = "X".repeat(160);
+        let folded = fold(&line);
+
+        assert!(folded.split("\r\n").all(|line| line.len() <= 75));
+        assert_eq!(folded.replace("\r\n ", ""), line);
+    }
+
+    #[test]
+    fn weekly() {
+        let now = "2025-07-23T12:00:00Z".parse().unwrap();
+        let events = weekly_events(now, Some(Archon::Boreal));
+
+        let first = &events[0];
+        assert_eq!(first.uid, "circuit-20250721");
+        assert_eq!(first.start, week_start(now));
+
+        let archons = events
+            .iter()
+            .filter(|event| event.uid.starts_with("archon-"))
+            .map(|event| event.summary.as_str())
+            .take(2)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            archons,
+            [
+                "Archon Boreal: Azure Archon Shard",
+                "Archon Amar: Crimson Archon Shard"
+            ]
+        );
+
+        let resets = events
+            .iter()
+            .filter(|event| event.uid.starts_with("weekly-reset-"))
+            .count();
+        assert_eq!(resets, WEEKS_AHEAD as usize - 1);
+    }
+}
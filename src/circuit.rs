@@ -1,54 +1,433 @@
-use chrono::{DateTime, TimeZone, Utc};
+use crate::handler::Handler;
+use crate::warning;
+
+use std::fmt::{self, Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+
+use anyhow::{Result, anyhow, bail};
+use chrono::{DateTime, TimeDelta, Utc};
 use once_cell::sync::Lazy;
+use serde::Deserialize;
+use warframe::worldstate::{Endpoint, Language, Queryable};
 
-// Most recent time of first rotation as of writing.
-#[rustfmt::skip]
-const EPOCH: Lazy<DateTime<Utc>> =
-    Lazy::new(|| Utc.with_ymd_and_hms(2025, 6, 9, 0, 0, 0).unwrap());
-
-// Most recent time of first rotation for the Steel Path as of writing.
-const SP_EPOCH: Lazy<DateTime<Utc>> =
-    Lazy::new(|| Utc.with_ymd_and_hms(2025, 7, 21, 0, 0, 0).unwrap());
-
-#[rustfmt::skip]
-const REWARDS: [[&str; 3]; 11] = [
-    ["Excalibur", "Trinity", "Ember"   ],
-    ["Loki",      "Mag",     "Rhino"   ],
-    ["Ash",       "Frost",   "Nyx"     ],
-    ["Saryn",     "Vauban",  "Nova"    ],
-    ["Nekros",    "Valkyr",  "Oberon"  ],
-    ["Hydroid",   "Mirage",  "Limbo"   ],
-    ["Mesa",      "Chroma",  "Atlas"   ],
-    ["Ivara",     "Inaros",  "Titania" ],
-    ["Nidus",     "Octavia", "Harrow"  ],
-    ["Gara",      "Khora",   "Revenant"],
-    ["Garuda",    "Baruuk",  "Hildryn" ],
-];
-
-#[rustfmt::skip]
-const SP_REWARDS: [[&str; 5]; 8] = [
-    ["Braton",      "Lato",          "Skana",      "Paris",     "Kunai"         ],
-    ["Boar",        "Gammacor",      "Angstrum",   "Gorgon",    "Anku"          ],
-    ["Bo",          "Latron",        "Furis",      "Furax",     "Strun"         ],
-    ["Lex",         "Magistar",      "Boltor",     "Bronco",    "Ceramic Dagger"],
-    ["Torid",       "Dual Toxocyst", "Dual Ichor", "Miter",     "Atomos"        ],
-    ["Ack & Brunt", "Soma",          "Vasto",      "Nami Solo", "Burston"       ],
-    ["Zylok",       "Sibear",        "Dread",      "Despair",   "Hate"          ],
-    ["Dera",        "Sybaris",       "Cestra",     "Sicarus",   "Okina"         ],
-];
+/// The rotations the bot was built with, used unless a file overrides them.
+const EMBEDDED: &str = include_str!("../data/circuit.json");
 
-/// Get the Steel Path offerings from the Circuit.
-pub fn sp_circuit() -> [&'static str; 5] {
-    let now = Utc::now();
-    let week = (now - *SP_EPOCH).num_weeks() % 8;
+/// The version of the rotation file format this build understands.
+const FORMAT_VERSION: u32 = 1;
+
+/// The file the rotations are loaded from, if they are not the embedded ones.
+static SOURCE: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// The rotations currently in use.
+static ROTATIONS: Lazy<RwLock<Arc<Rotations>>> =
+    Lazy::new(|| RwLock::new(Arc::new(Rotations::embedded())));
+
+/// One of the Circuit's two reward tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitKind {
+    /// Warframes, from the normal Circuit.
+    Normal,
+    /// Incarnon weapons, from the Steel Path Circuit.
+    SteelPath,
+}
+
+impl Display for CircuitKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CircuitKind::Normal => write!(f, "The Circuit"),
+            CircuitKind::SteelPath => write!(f, "The Circuit (Steel Path)"),
+        }
+    }
+}
+
+/// When an item will next be offered by the Circuit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Availability {
+    pub item: String,
+    pub kind: CircuitKind,
+    /// How many weeks from now the item is offered. `0` if it is offered this week.
+    pub weeks_ahead: i64,
+    /// The start of the week the item is offered in.
+    pub start: DateTime<Utc>,
+}
+
+/// The weekly rotation of one of the Circuit's reward tables.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Rotation {
+    /// The start of a week in which the first rewards were offered.
+    pub epoch: DateTime<Utc>,
+    /// The rewards offered each week, in order.
+    pub rewards: Vec<Vec<String>>,
+}
+
+/// The rotations of both of the Circuit's reward tables, as stored in the rotation file.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Rotations {
+    pub version: u32,
+    pub normal: Rotation,
+    pub steel_path: Rotation,
+}
+
+/// The Circuit's offerings for one week, as reported by the worldstate.
+#[derive(Debug, Clone, Deserialize)]
+pub struct LiveChoices {
+    /// `normal` for warframes and `hard` for incarnon weapons.
+    pub category: String,
+    pub choices: Vec<String>,
+}
+
+/// The number of whole weeks from the epoch to the given time, which is negative before it.
+fn weeks_since(epoch: DateTime<Utc>, time: DateTime<Utc>) -> i64 {
+    (time - epoch)
+        .num_seconds()
+        .div_euclid(TimeDelta::weeks(1).num_seconds())
+}
+
+impl Rotation {
+    /// The rewards offered in the week containing the given time.
+    pub fn at(&self, time: DateTime<Utc>) -> &[String] {
+        let week = weeks_since(self.epoch, time).rem_euclid(self.rewards.len() as i64);
+
+        &self.rewards[week as usize]
+    }
+
+    /// The start of the week containing the given time.
+    pub fn week_start(&self, time: DateTime<Utc>) -> DateTime<Utc> {
+        self.epoch + TimeDelta::weeks(weeks_since(self.epoch, time))
+    }
+}
+
+impl Rotations {
+    /// Parse and validate the contents of a rotation file.
+    pub fn parse(contents: &str) -> Result<Self> {
+        let rotations: Self = serde_json::from_str(contents)?;
+
+        if rotations.version != FORMAT_VERSION {
+            bail!(
+                "unsupported rotation file version {}, expected {FORMAT_VERSION}",
+                rotations.version
+            );
+        }
+        for (kind, rotation) in rotations.tables() {
+            if rotation.rewards.is_empty() || rotation.rewards.iter().any(Vec::is_empty) {
+                bail!("the rotation of {kind} has an empty week");
+            }
+        }
+
+        Ok(rotations)
+    }
+
+    /// The rotations the bot was built with.
+    pub fn embedded() -> Self {
+        Self::parse(EMBEDDED).expect("the embedded rotation file is valid")
+    }
+
+    /// Load the rotations from the given file, or the embedded ones if there is none.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let Some(path) = path else {
+            return Ok(Self::embedded());
+        };
+
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("could not read {}: {e}", path.display()))?;
+        Self::parse(&contents).map_err(|e| anyhow!("could not parse {}: {e}", path.display()))
+    }
+
+    /// Both reward tables, along with which they are.
+    fn tables(&self) -> [(CircuitKind, &Rotation); 2] {
+        [
+            (CircuitKind::Normal, &self.normal),
+            (CircuitKind::SteelPath, &self.steel_path),
+        ]
+    }
+
+    /// The rotation of the given reward table.
+    pub fn rotation(&self, kind: CircuitKind) -> &Rotation {
+        match kind {
+            CircuitKind::Normal => &self.normal,
+            CircuitKind::SteelPath => &self.steel_path,
+        }
+    }
+
+    /// Every item the Circuit offers, along with the table it is in.
+    pub fn all_rewards(&self) -> impl Iterator<Item = (CircuitKind, &str)> {
+        self.tables().into_iter().flat_map(|(kind, rotation)| {
+            rotation
+                .rewards
+                .iter()
+                .flatten()
+                .map(move |item| (kind, item.as_str()))
+        })
+    }
+
+    /// Find the item with the given name, ignoring case, among the Circuit's rewards.
+    pub fn find_reward(&self, name: &str) -> Option<(CircuitKind, &str)> {
+        self.all_rewards()
+            .find(|(_, item)| item.eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Find when the item with the given name, ignoring case, is next offered, starting from the
+    /// week containing the given time. `None` if the Circuit never offers it.
+    pub fn next_available(&self, name: &str, now: DateTime<Utc>) -> Option<Availability> {
+        let (kind, item) = self.find_reward(name)?;
+        let rotation = self.rotation(kind);
+
+        (0..rotation.rewards.len() as i64).find_map(|weeks_ahead| {
+            let time = now + TimeDelta::weeks(weeks_ahead);
+
+            rotation
+                .at(time)
+                .iter()
+                .any(|offered| offered == item)
+                .then(|| Availability {
+                    item: item.to_string(),
+                    kind,
+                    weeks_ahead,
+                    start: rotation.week_start(time),
+                })
+        })
+    }
+
+    /// Compare the offerings reported by the worldstate with the computed ones for the week
+    /// containing the given time. Returns a description of each table that disagrees.
+    pub fn drift(&self, live: &[LiveChoices], now: DateTime<Utc>) -> Vec<String> {
+        live.iter()
+            .filter_map(|live| {
+                let kind = match live.category.as_str() {
+                    "normal" => CircuitKind::Normal,
+                    "hard" => CircuitKind::SteelPath,
+                    _ => return None,
+                };
+
+                let mut expected = self.rotation(kind).at(now).to_vec();
+                let mut actual = live.choices.clone();
+                expected.sort();
+                actual.sort();
+
+                (expected != actual).then(|| {
+                    format!(
+                        "{kind} offers {} this week, but the rotation file says {}",
+                        actual.join(", "),
+                        expected.join(", ")
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+/// Set the file the rotations are loaded from, or `None` for the embedded ones, and load them.
+pub fn set_source(path: Option<PathBuf>) -> Result<()> {
+    *SOURCE.lock().unwrap() = path;
+
+    reload().map(|_| ())
+}
+
+/// Load the rotations again from their source, keeping the current ones if that fails.
+///
+/// Returns a description of where they were loaded from.
+pub fn reload() -> Result<String> {
+    let source = SOURCE.lock().unwrap().clone();
+    let rotations = Rotations::load(source.as_deref())?;
+    *ROTATIONS.write().unwrap() = Arc::new(rotations);
+
+    Ok(match source {
+        Some(path) => path.display().to_string(),
+        None => "the embedded rotation file".to_string(),
+    })
+}
 
-    SP_REWARDS[week as usize]
+/// The rotations currently in use.
+pub fn rotations() -> Arc<Rotations> {
+    ROTATIONS.read().unwrap().clone()
+}
+
+/// Duviri's cycle in the worldstate, which reports the Circuit's current offerings. The warframe
+/// crate has no model for it, so it is queried like one here.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DuviriCycle {
+    #[serde(default)]
+    pub choices: Vec<LiveChoices>,
+}
+
+impl Endpoint for DuviriCycle {
+    fn endpoint_en(base_url: &str) -> String {
+        format!("{base_url}/pc/duviriCycle/?language=en")
+    }
+
+    fn endpoint(base_url: &str, language: Language) -> String {
+        format!("{base_url}/pc/duviriCycle/?language={language}")
+    }
+}
+
+impl Queryable for DuviriCycle {
+    type Return = Self;
+}
+
+/// Warn about any disagreement between the worldstate's view of this week's offerings and the
+/// rotation file.
+pub async fn check_drift(handler: &Handler) -> Result<()> {
+    let Some(live) = handler.circuit_choices().await? else {
+        return Ok(());
+    };
+
+    for drift in rotations().drift(&live, Utc::now()) {
+        warning!(context = "circuit rotation", "{drift}");
+    }
+
+    Ok(())
+}
+
+/// The start of the Circuit's week containing the given time.
+pub fn week_start(time: DateTime<Utc>) -> DateTime<Utc> {
+    rotations().normal.week_start(time)
+}
+
+/// Get the Steel Path offerings from the Circuit in the week containing the given time.
+pub fn sp_circuit_at(time: DateTime<Utc>) -> Vec<String> {
+    rotations().steel_path.at(time).to_vec()
+}
+
+/// Get the offerings from the Circuit in the week containing the given time.
+pub fn circuit_at(time: DateTime<Utc>) -> Vec<String> {
+    rotations().normal.at(time).to_vec()
+}
+
+/// Get the Steel Path offerings from the Circuit.
+pub fn sp_circuit() -> Vec<String> {
+    sp_circuit_at(Utc::now())
 }
 
 /// Get the offerings from the Circuit.
-pub fn circuit() -> [&'static str; 3] {
-    let now = Utc::now();
-    let week = (now - *EPOCH).num_weeks() % 11;
+pub fn circuit() -> Vec<String> {
+    circuit_at(Utc::now())
+}
+
+/// Every item the Circuit offers, along with the table it is in.
+pub fn all_rewards() -> Vec<(CircuitKind, String)> {
+    rotations()
+        .all_rewards()
+        .map(|(kind, item)| (kind, item.to_string()))
+        .collect()
+}
+
+/// Find the item with the given name, ignoring case, among the Circuit's rewards.
+pub fn find_reward(name: &str) -> Option<(CircuitKind, String)> {
+    rotations()
+        .find_reward(name)
+        .map(|(kind, item)| (kind, item.to_string()))
+}
+
+/// Find when the item with the given name, ignoring case, is next offered by the Circuit, starting
+/// from the week containing the given time. `None` if the Circuit never offers it.
+pub fn next_available(name: &str, now: DateTime<Utc>) -> Option<Availability> {
+    rotations().next_available(name, now)
+}
+
+#[cfg(test)]
+mod circuit_test {
+    use super::*;
+
+    fn monday(date: &str) -> DateTime<Utc> {
+        format!("{date}T00:00:00Z").parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn duviri_cycle_query() {
+        use crate::test_util::stand_in;
+        use warframe::worldstate::Client;
+
+        let body = r#"{"state":"joy","choices":[{"category":"normal","choices":["Excalibur"]}]}"#;
+        let (url, server) = stand_in(&[("200 OK", body)]).await;
+
+        let client = Client::new(reqwest::Client::new(), url);
+        let cycle = client.fetch::<DuviriCycle>().await.unwrap();
+        assert_eq!(cycle.choices[0].choices, ["Excalibur"]);
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("GET /pc/duviriCycle/?language=en "));
+    }
+
+    #[test]
+    fn rotation_wraps_around() {
+        let rotations = Rotations::embedded();
+        let (normal, steel_path) = (&rotations.normal, &rotations.steel_path);
+        let epoch = normal.epoch;
+
+        assert_eq!(normal.at(epoch), ["Excalibur", "Trinity", "Ember"]);
+        assert_eq!(normal.at(epoch + TimeDelta::weeks(11)), normal.rewards[0]);
+        assert_eq!(normal.at(epoch - TimeDelta::seconds(1)), normal.rewards[10]);
+        assert_eq!(
+            steel_path.at(steel_path.epoch + TimeDelta::weeks(9)),
+            steel_path.rewards[1]
+        );
+    }
+
+    #[test]
+    fn week_start_is_monday() {
+        let rotation = Rotations::embedded().normal;
+        let wednesday = "2025-07-23T12:00:00Z".parse().unwrap();
+
+        assert_eq!(rotation.week_start(wednesday), monday("2025-07-21"));
+        assert_eq!(
+            rotation.week_start(monday("2025-07-21")),
+            monday("2025-07-21")
+        );
+    }
+
+    #[test]
+    fn find_next_available() {
+        let rotations = Rotations::embedded();
+        let epoch = rotations.normal.epoch;
+        let now = epoch + TimeDelta::days(2);
+
+        let this_week = rotations.next_available("excalibur", now).unwrap();
+        assert_eq!(this_week.item, "Excalibur");
+        assert_eq!(this_week.kind, CircuitKind::Normal);
+        assert_eq!(this_week.weeks_ahead, 0);
+        assert_eq!(this_week.start, epoch);
+
+        let later = rotations.next_available("Nyx", now).unwrap();
+        assert_eq!(later.weeks_ahead, 2);
+        assert_eq!(later.start, epoch + TimeDelta::weeks(2));
+
+        let steel_path = rotations.next_available("Soma", now).unwrap();
+        assert_eq!(steel_path.kind, CircuitKind::SteelPath);
+        assert!(
+            rotations
+                .steel_path
+                .at(steel_path.start)
+                .contains(&"Soma".to_string())
+        );
+
+        assert_eq!(rotations.next_available("Lotus", now), None);
+    }
+
+    #[test]
+    fn parse() {
+        assert!(Rotations::parse(&EMBEDDED.replace(r#""version": 1"#, r#""version": 2"#)).is_err());
+        assert!(Rotations::parse(&EMBEDDED.replace(r#"["Loki", "Mag", "Rhino"]"#, "[]")).is_err());
+        assert!(Rotations::parse("{}").is_err());
+    }
+
+    #[test]
+    fn drift() {
+        let rotations = Rotations::embedded();
+        let now = rotations.normal.epoch + TimeDelta::days(1);
+        let live = |category: &str, choices: &[&str]| LiveChoices {
+            category: category.into(),
+            choices: choices.iter().map(|choice| choice.to_string()).collect(),
+        };
+
+        let agreeing = [
+            live("normal", &["Ember", "Excalibur", "Trinity"]),
+            live("unknown", &["Lotus"]),
+        ];
+        assert!(rotations.drift(&agreeing, now).is_empty());
 
-    REWARDS[week as usize]
+        let disagreeing = [live("normal", &["Loki", "Mag", "Rhino"])];
+        assert_eq!(rotations.drift(&disagreeing, now).len(), 1);
+    }
 }
@@ -1,4 +1,14 @@
-use clap::Parser;
+use crate::alerts::AlertDestination;
+use crate::events::EventFeed;
+use crate::handler::Destination;
+use crate::webhook::Webhook;
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use poise::serenity_prelude::GuildId;
 
 /// A Discord bot that interacts with the WarframeStatus API to send news to a give Discord channel.
 /// For help see https://github.com/4jamesccraven/warframe-bot
@@ -6,14 +16,232 @@ use clap::Parser;
 pub struct Cli {
     /// A valid token from the Discord Developer Portal for a discord bot.
     #[arg(long, env = "WF_DISCORDTOKEN")]
-    pub api_token: String,
+    pub api_token: Option<String>,
 
     /// A valid ID for a Discord channel for which the given bot has permissions.
     #[arg(long, env = "WF_CHANNELID")]
-    pub channel_id: u64,
+    pub channel_id: Option<u64>,
 
     /// A valid ID for a Discord server that should be used to register the slash commands. Global
     /// assumed if no id is provided.
     #[arg(long, env = "WF_GUILDID")]
     pub guild_id: Option<u64>,
+
+    /// One or more Discord webhook URLs to send notifications to instead of a channel. In this
+    /// mode no Discord token is needed, and slash commands are disabled.
+    #[arg(long = "webhook-url", env = "WF_WEBHOOKURLS", value_delimiter = ',')]
+    pub webhooks: Vec<Webhook>,
+
+    /// How many of the most recent news items to post on the first run, or after the news cache
+    /// is lost. Older news items are silently marked as seen.
+    #[arg(long, env = "WF_BACKFILL", default_value_t = 0)]
+    pub backfill: usize,
+
+    /// Post a reminder, including his relay, each of these numbers of hours before Baro Ki'Teer
+    /// arrives. Users that opt in with `/baro_reminders` also get it as a direct message.
+    #[arg(long, env = "WF_BAROARRIVALREMINDERS", value_delimiter = ',')]
+    pub baro_arrival_reminders: Vec<u32>,
+
+    /// Post a reminder this many hours before Baro Ki'Teer leaves. His inventory posts are always
+    /// updated once he has left.
+    #[arg(long, env = "WF_BAROLEAVINGREMINDER")]
+    pub baro_leaving_reminder: Option<u32>,
+
+    /// Keep a Discord scheduled event for Baro Ki'Teer's next visit in the guild given by
+    /// `--guild-id`. Needs the Manage Events permission.
+    #[arg(long, env = "WF_SCHEDULEDEVENTS", conflicts_with = "webhooks")]
+    pub scheduled_events: bool,
+
+    /// Also keep a scheduled event for the next weekly reset.
+    #[arg(long, env = "WF_WEEKLYEVENT", requires = "scheduled_events")]
+    pub weekly_event: bool,
+
+    /// A channel to show the current timers in. Voice channels are renamed to Baro Ki'Teer's
+    /// countdown, and other channels get the timers as their topic. Updated every ten minutes.
+    #[arg(long, env = "WF_TIMERCHANNEL", conflicts_with = "webhooks")]
+    pub timer_channel: Option<u64>,
+
+    /// A channel to alert about persistent problems, such as repeated failures to fetch the
+    /// worldstate or to deliver messages. A notice follows once each problem clears.
+    #[arg(long, env = "WF_ALERTCHANNEL", conflicts_with_all = ["webhooks", "alert_user"])]
+    pub alert_channel: Option<u64>,
+
+    /// A user to alert about persistent problems by direct message, instead of a channel.
+    #[arg(long, env = "WF_ALERTUSER", conflicts_with = "webhooks")]
+    pub alert_user: Option<u64>,
+
+    /// An address to serve HTTP endpoints on, e.g., `127.0.0.1:8080`. Serves health checks, a JSON
+    /// API and an iCalendar of upcoming events.
+    #[arg(long, env = "WF_HTTPADDR")]
+    pub http_addr: Option<SocketAddr>,
+
+    /// An address to serve Prometheus metrics on at `/metrics`, e.g., `127.0.0.1:9100`.
+    #[arg(long, env = "WF_METRICSADDR")]
+    pub metrics_addr: Option<SocketAddr>,
+
+    /// A file to load The Circuit's rotations from, instead of the ones the bot was built with.
+    /// It is read again when the bot receives SIGHUP or an owner uses `/circuit reload`.
+    #[arg(long, env = "WF_CIRCUITFILE")]
+    pub circuit_file: Option<PathBuf>,
+
+    /// How to format the logs written to stderr. Which logs are shown is set by `WF_LOG`, e.g.,
+    /// `WF_LOG=debug`.
+    #[arg(long, env = "WF_LOGFORMAT", value_enum, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+
+    /// One or more HTTP endpoints that receive a JSON event for every notification the bot sends.
+    #[arg(
+        long = "event-webhook",
+        env = "WF_EVENTWEBHOOKS",
+        value_delimiter = ','
+    )]
+    pub event_webhooks: Vec<String>,
+
+    /// A secret used to sign event payloads. The signature is sent in the `X-WF-Bot-Signature`
+    /// header as `sha256=<hex digest>`.
+    #[arg(long, env = "WF_EVENTSECRET")]
+    pub event_secret: Option<String>,
+
+    /// Run a one-off command instead of starting the bot.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum Command {
+    /// Print information to the terminal. Does not require a Discord token.
+    Print {
+        /// The information to print.
+        #[arg(value_enum)]
+        subject: PrintSubject,
+
+        /// The format to print the information in.
+        #[arg(long, value_enum, default_value_t = OutputFormat::Plain)]
+        format: OutputFormat,
+    },
+
+    /// Perform the given notifications once and exit, e.g., from a systemd timer or cron job.
+    RunOnce {
+        /// A comma-separated list of notifications to perform.
+        #[arg(long, value_enum, value_delimiter = ',', required = true)]
+        tasks: Vec<Task>,
+    },
+}
+
+/// Information that can be printed with `wf-bot print`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PrintSubject {
+    /// When Baro Ki'Teer will be here next, or his inventory if he's here.
+    Baro,
+    /// Recent news.
+    News,
+    /// The Circuit and Archon Hunt for this week.
+    Weekly,
+    /// The Circuit for this week.
+    Circuit,
+}
+
+/// Notifications that can be performed with `wf-bot run-once`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum)]
+pub enum Task {
+    /// Send unseen news.
+    News,
+    /// Send Baro Ki'Teer's inventory, if he is here, and update it once he has left. Also sends
+    /// any reminder of his arrival that is due.
+    Baro,
+    /// Send The Circuit and Archon Hunt for this week.
+    Weekly,
+}
+
+impl std::fmt::Display for Task {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Every variant has a possible value, as none are skipped.
+        let value = self.to_possible_value().unwrap();
+        write!(f, "{}", value.get_name())
+    }
+}
+
+/// Output formats for `wf-bot print`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// The messages exactly as the bot would send them.
+    Plain,
+    /// Structured JSON for use in scripts.
+    Json,
+}
+
+/// Formats for the bot's logs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable lines, coloured when written to a terminal.
+    Text,
+    /// One JSON object per line, for journald, Loki and the like.
+    Json,
+}
+
+impl Cli {
+    /// Get the Discord token, exiting with a usage error if none was given.
+    pub fn api_token(&self) -> &str {
+        self.api_token
+            .as_deref()
+            .unwrap_or_else(|| missing_argument("--api-token <API_TOKEN>"))
+    }
+
+    /// Get the news channel ID, exiting with a usage error if none was given.
+    pub fn channel_id(&self) -> u64 {
+        self.channel_id
+            .unwrap_or_else(|| missing_argument("--channel-id <CHANNEL_ID>"))
+    }
+
+    /// Get the guild to keep scheduled events in, if they are enabled. Exits with a usage error
+    /// if no guild was given.
+    pub fn event_guild(&self) -> Option<GuildId> {
+        if !self.scheduled_events {
+            return None;
+        }
+
+        let guild_id = self
+            .guild_id
+            .unwrap_or_else(|| missing_argument("--guild-id <GUILD_ID>"));
+        Some(guild_id.into())
+    }
+
+    /// Get the feed of events for generic webhooks.
+    pub fn event_feed(&self) -> EventFeed {
+        EventFeed::new(self.event_webhooks.clone(), self.event_secret.clone())
+    }
+
+    /// Get where to alert about persistent problems, if anywhere.
+    pub fn alert_destination(&self) -> Option<AlertDestination> {
+        match (self.alert_channel, self.alert_user) {
+            (Some(channel_id), _) => Some(AlertDestination::Channel(channel_id.into())),
+            (None, Some(user_id)) => Some(AlertDestination::User(user_id.into())),
+            (None, None) => None,
+        }
+    }
+
+    /// Returns `true` if notifications should be sent to webhooks rather than through the bot.
+    pub fn webhook_only(&self) -> bool {
+        !self.webhooks.is_empty()
+    }
+
+    /// Get where notifications should be delivered, exiting with a usage error if neither a
+    /// channel nor a webhook was given.
+    pub fn destination(&self) -> Destination {
+        if self.webhook_only() {
+            Destination::Webhooks(self.webhooks.clone())
+        } else {
+            Destination::Channel(self.channel_id().into())
+        }
+    }
+}
+
+/// Exit in the same way clap does when a required argument is missing.
+fn missing_argument(arg: &str) -> ! {
+    Cli::command()
+        .error(
+            ErrorKind::MissingRequiredArgument,
+            format!("the following required arguments were not provided:\n  {arg}"),
+        )
+        .exit()
 }
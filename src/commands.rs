@@ -1,8 +1,15 @@
-use crate::handler::Handler;
+use crate::archon::Shard;
+use crate::circuit::{
+    self, all_rewards, circuit_at, find_reward, next_available, sp_circuit_at, week_start,
+};
+use crate::handler::{Handler, Wishlist};
 use crate::warning;
+use crate::{DateStyle, MESSAGE_LIMIT, fmt_api_date, join_lines};
 
 use anyhow::{Error, Result};
-use poise::command;
+use chrono::{DateTime, NaiveDate, NaiveTime, TimeDelta, Utc};
+use poise::serenity_prelude::CreateAttachment;
+use poise::{CreateReply, command};
 
 type Context<'a> = poise::Context<'a, Handler, Error>;
 
@@ -10,11 +17,11 @@ type Context<'a> = poise::Context<'a, Handler, Error>;
 #[command(slash_command, guild_cooldown = 360)]
 pub async fn baro(ctx: Context<'_>) -> Result<()> {
     let handler = ctx.data();
-    let messages = handler.baro_messages().await;
-
-    if messages.is_empty() {
+    let Ok(messages) = handler.baro_messages().await else {
         ctx.say("Internal error, try again soon.").await?;
-    }
+        return Ok(());
+    };
+
     for msg in messages.into_iter() {
         if let Err(e) = ctx.say(msg).await {
             warning!(context = "sending message", "{e}");
@@ -24,17 +31,92 @@ pub async fn baro(ctx: Context<'_>) -> Result<()> {
     Ok(())
 }
 
-/// Show unseen news
+/// Get a direct message before Baro Ki'Teer arrives
+#[command(slash_command, user_cooldown = 10)]
+pub async fn baro_reminders(
+    ctx: Context<'_>,
+    #[description = "Whether to get a direct message before Baro Ki'Teer arrives"] enabled: bool,
+) -> Result<()> {
+    let handler = ctx.data();
+    let changed = match enabled && !handler.reminds_of_baro_arrival() {
+        true => None,
+        false => Some(handler.set_baro_reminders(ctx.author().id, enabled).await),
+    };
+
+    let msg = match (enabled, changed) {
+        (_, None) => "This bot does not send reminders before Baro Ki'Teer arrives.",
+        (true, Some(true)) => "You will get a direct message before Baro Ki'Teer arrives.",
+        (true, Some(false)) => "You are already getting reminders for Baro Ki'Teer.",
+        (false, Some(true)) => "You will no longer get reminders for Baro Ki'Teer.",
+        (false, Some(false)) => "You are not getting reminders for Baro Ki'Teer.",
+    };
+    ctx.send(CreateReply::default().content(msg).ephemeral(true))
+        .await?;
+
+    Ok(())
+}
+
+/// Post a dashboard of timers in this channel, which is kept up to date
+#[command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "MANAGE_CHANNELS",
+    required_permissions = "MANAGE_CHANNELS"
+)]
+pub async fn dashboard(ctx: Context<'_>) -> Result<()> {
+    ctx.defer_ephemeral().await?;
+
+    let handler = ctx.data();
+    let msg = match handler.create_dashboard(ctx.channel_id()).await {
+        Ok(()) => "Dashboard posted. It will be updated every minute.",
+        Err(e) => {
+            warning!(context = "posting dashboard", "{e}");
+            "Could not post the dashboard. Check that I can send messages here."
+        }
+    };
+    ctx.send(CreateReply::default().content(msg).ephemeral(true))
+        .await?;
+
+    Ok(())
+}
+
+/// Show recent news
 #[command(slash_command, guild_cooldown = 360)]
-pub async fn news(ctx: Context<'_>) -> Result<()> {
+pub async fn news(
+    ctx: Context<'_>,
+    #[description = "How many news items to show (default 5)"]
+    #[min = 1]
+    #[max = 20]
+    count: Option<usize>,
+    #[description = "Only show news since this date, e.g., 2025-07-21"] since: Option<String>,
+) -> Result<()> {
+    let since = match since.as_deref().map(parse_date).transpose() {
+        Ok(since) => since,
+        Err(_) => {
+            ctx.say("Dates should look like 2025-07-21.").await?;
+            return Ok(());
+        }
+    };
+
     let handler = ctx.data();
-    let messages = handler.news_messages().await;
+    let Ok(snapshot) = handler.latest_news(count.unwrap_or(5), since).await else {
+        ctx.say("Internal error, try again soon.").await?;
+        return Ok(());
+    };
 
-    if messages.is_empty() {
+    if let Some(note) = snapshot.staleness_note(Utc::now()) {
+        ctx.say(note).await?;
+    }
+    if snapshot.value.is_empty() {
         ctx.say("No news to show.").await?;
     }
-    for msg in messages.into_iter() {
-        if let Err(e) = ctx.say(msg).await {
+    let items: Vec<String> = snapshot
+        .value
+        .iter()
+        .map(|news_item| news_item.as_message(DateStyle::Discord))
+        .collect();
+    for message in join_lines(&items, MESSAGE_LIMIT) {
+        if let Err(e) = ctx.say(message).await {
             warning!(context = "sending message", "{e}");
         }
     }
@@ -42,6 +124,12 @@ pub async fn news(ctx: Context<'_>) -> Result<()> {
     Ok(())
 }
 
+/// Parse a date given as an argument to a command, as the start of that day in UTC.
+fn parse_date(date: &str) -> Result<DateTime<Utc>> {
+    let date = NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")?;
+    Ok(date.and_time(NaiveTime::MIN).and_utc())
+}
+
 /// Show what's in The Circuit, and what Archon Shard is available from this week's Archon Hunt.
 #[command(slash_command, guild_cooldown = 360)]
 pub async fn weekly(ctx: Context<'_>) -> Result<()> {
@@ -49,7 +137,7 @@ pub async fn weekly(ctx: Context<'_>) -> Result<()> {
     let message = handler
         .weekly_messages()
         .await
-        .unwrap_or("Internal error, try again soon.".into());
+        .unwrap_or_else(|_| "Internal error, try again soon.".into());
 
     if let Err(e) = ctx.say(&message).await {
         warning!(context = "sending message", "{e}");
@@ -58,12 +146,296 @@ pub async fn weekly(ctx: Context<'_>) -> Result<()> {
     Ok(())
 }
 
+/// Show this week's Archon Hunt, and which Archons come after it
+#[command(slash_command, guild_cooldown = 60)]
+pub async fn archon(
+    ctx: Context<'_>,
+    #[description = "How many weeks ahead to show (default 3)"]
+    #[max = 12]
+    weeks_ahead: Option<u32>,
+) -> Result<()> {
+    let handler = ctx.data();
+    let message = handler
+        .archon_messages(weeks_ahead.unwrap_or(3))
+        .await
+        .unwrap_or_else(|_| "Internal error, try again soon.".into());
+
+    if let Err(e) = ctx.say(&message).await {
+        warning!(context = "sending message", "{e}");
+    }
+
+    Ok(())
+}
+
+/// Get told on the weekly reset when the Archon Hunt drops a colour of shard
+#[command(slash_command, user_cooldown = 5)]
+pub async fn archon_reminders(
+    ctx: Context<'_>,
+    #[description = "The colour of shard"] shard: Shard,
+    #[description = "Whether to be told when the Archon Hunt drops this shard"] enabled: bool,
+    #[description = "Get a direct message instead of a mention in the weekly post (default false)"]
+    direct_message: Option<bool>,
+) -> Result<()> {
+    let handler = ctx.data();
+    let user = ctx.author().id;
+    let shard = shard.to_string();
+
+    let changed = match enabled {
+        true => {
+            let direct_message = direct_message.unwrap_or(false);
+            handler
+                .subscribe_item(Wishlist::ArchonShard, user, &shard, direct_message)
+                .await
+        }
+        false => {
+            handler
+                .unsubscribe_item(Wishlist::ArchonShard, user, &shard)
+                .await
+        }
+    };
+
+    let msg = match (enabled, changed) {
+        (true, true) => format!("You will be told when the Archon Hunt drops the {shard}."),
+        (true, false) => format!("You are already waiting for the {shard}."),
+        (false, true) => format!("You will no longer be told about the {shard}."),
+        (false, false) => format!("You are not waiting for the {shard}."),
+    };
+    ctx.send(CreateReply::default().content(msg).ephemeral(true))
+        .await?;
+
+    Ok(())
+}
+
+/// Get a calendar of upcoming resets, rotations and Baro Ki'Teer's visits
+#[command(slash_command, guild_cooldown = 60)]
+pub async fn calendar(ctx: Context<'_>) -> Result<()> {
+    ctx.defer().await?;
+
+    let handler = ctx.data();
+    let calendar = handler.calendar().await;
+
+    let attachment = CreateAttachment::bytes(calendar.into_bytes(), "warframe.ics");
+    let reply = CreateReply::default()
+        .content("Open this file to import the next few weeks into your calendar.")
+        .attachment(attachment);
+    ctx.send(reply).await?;
+
+    Ok(())
+}
+
+/// Show upcoming rotations of The Circuit, or find when something is offered
+#[command(
+    slash_command,
+    subcommands(
+        "circuit_rotation",
+        "circuit_find",
+        "circuit_subscribe",
+        "circuit_unsubscribe",
+        "circuit_subscriptions",
+        "circuit_reload"
+    ),
+    subcommand_required
+)]
+pub async fn circuit(_ctx: Context<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// Show what The Circuit offers this week and in the coming weeks
+#[command(slash_command, rename = "rotation", guild_cooldown = 60)]
+pub async fn circuit_rotation(
+    ctx: Context<'_>,
+    #[description = "How many weeks ahead to show (default 3)"]
+    #[max = 11]
+    weeks_ahead: Option<u32>,
+) -> Result<()> {
+    let now = Utc::now();
+    let mut lines = vec![];
+
+    for week in 0..=weeks_ahead.unwrap_or(3) {
+        let time = now + TimeDelta::weeks(week.into());
+        let heading = match week {
+            0 => "This week".to_string(),
+            _ => format!(
+                "Week of {}",
+                fmt_api_date(&week_start(time), DateStyle::Discord)
+            ),
+        };
+
+        lines.push(format!(
+            "**{heading}**\n- The Circuit: {}\n- The Circuit (Steel Path): {}",
+            circuit_at(time).join(", "),
+            sp_circuit_at(time).join(", "),
+        ));
+    }
+
+    ctx.say(lines.join("\n")).await?;
+
+    Ok(())
+}
+
+/// Find when The Circuit next offers a warframe or incarnon weapon
+#[command(slash_command, rename = "find", guild_cooldown = 10)]
+pub async fn circuit_find(
+    ctx: Context<'_>,
+    #[description = "The warframe or incarnon weapon to look for"]
+    #[autocomplete = "autocomplete_reward"]
+    item: String,
+) -> Result<()> {
+    let Some(availability) = next_available(&item, Utc::now()) else {
+        ctx.send(
+            CreateReply::default()
+                .content(format!("The Circuit does not offer {item}."))
+                .ephemeral(true),
+        )
+        .await?;
+        return Ok(());
+    };
+
+    let msg = match availability.weeks_ahead {
+        0 => format!(
+            "{} is in {} this week.",
+            availability.item, availability.kind
+        ),
+        weeks => format!(
+            "{} is in {} in {weeks} week{}, from {}.",
+            availability.item,
+            availability.kind,
+            if weeks == 1 { "" } else { "s" },
+            fmt_api_date(&availability.start, DateStyle::Discord),
+        ),
+    };
+    ctx.say(msg).await?;
+
+    Ok(())
+}
+
+/// Get told on the weekly reset when The Circuit offers a warframe or incarnon weapon
+#[command(slash_command, rename = "subscribe", user_cooldown = 5)]
+pub async fn circuit_subscribe(
+    ctx: Context<'_>,
+    #[description = "The warframe or incarnon weapon to wait for"]
+    #[autocomplete = "autocomplete_reward"]
+    item: String,
+    #[description = "Get a direct message instead of a mention in the weekly post (default false)"]
+    direct_message: Option<bool>,
+) -> Result<()> {
+    let msg = match find_reward(&item) {
+        None => format!("The Circuit does not offer {item}."),
+        Some((kind, item)) => {
+            let handler = ctx.data();
+            let direct_message = direct_message.unwrap_or(false);
+
+            match handler
+                .subscribe_item(Wishlist::Circuit, ctx.author().id, &item, direct_message)
+                .await
+            {
+                true => format!("You will be told when {item} is in {kind}."),
+                false => format!("You are already waiting for {item}."),
+            }
+        }
+    };
+    ctx.send(CreateReply::default().content(msg).ephemeral(true))
+        .await?;
+
+    Ok(())
+}
+
+/// Stop waiting for a warframe or incarnon weapon from The Circuit
+#[command(slash_command, rename = "unsubscribe", user_cooldown = 5)]
+pub async fn circuit_unsubscribe(
+    ctx: Context<'_>,
+    #[description = "The warframe or incarnon weapon to stop waiting for"]
+    #[autocomplete = "autocomplete_reward"]
+    item: String,
+) -> Result<()> {
+    let handler = ctx.data();
+    let item = find_reward(&item).map_or(item, |(_, item)| item);
+
+    let msg = match handler
+        .unsubscribe_item(Wishlist::Circuit, ctx.author().id, &item)
+        .await
+    {
+        true => format!("You will no longer be told about {item}."),
+        false => format!("You are not waiting for {item}."),
+    };
+    ctx.send(CreateReply::default().content(msg).ephemeral(true))
+        .await?;
+
+    Ok(())
+}
+
+/// Show which warframes and incarnon weapons you are waiting for
+#[command(slash_command, rename = "subscriptions", user_cooldown = 5)]
+pub async fn circuit_subscriptions(ctx: Context<'_>) -> Result<()> {
+    let handler = ctx.data();
+
+    let msg = match handler
+        .item_subscription(Wishlist::Circuit, ctx.author().id)
+        .await
+    {
+        None => "You are not waiting for anything from The Circuit.".to_string(),
+        Some(subscription) => {
+            let items = subscription.items.into_iter().collect::<Vec<_>>();
+            let delivery = match subscription.direct_message {
+                true => "a direct message",
+                false => "a mention in the weekly post",
+            };
+
+            format!("You are waiting for {}, by {delivery}.", items.join(", "))
+        }
+    };
+    ctx.send(CreateReply::default().content(msg).ephemeral(true))
+        .await?;
+
+    Ok(())
+}
+
+/// Load The Circuit's rotations again, after the rotation file has changed
+#[command(slash_command, rename = "reload", owners_only)]
+pub async fn circuit_reload(ctx: Context<'_>) -> Result<()> {
+    let msg = match circuit::reload() {
+        Ok(source) => format!("Reloaded The Circuit's rotations from {source}."),
+        Err(e) => {
+            warning!(context = "reloading circuit rotations", "{e}");
+            format!("Could not reload The Circuit's rotations, keeping the current ones: {e}")
+        }
+    };
+    ctx.send(CreateReply::default().content(msg).ephemeral(true))
+        .await?;
+
+    Ok(())
+}
+
+/// Suggest the Circuit's rewards that contain what has been typed so far.
+async fn autocomplete_reward<'a>(
+    _ctx: Context<'_>,
+    partial: &'a str,
+) -> impl Iterator<Item = String> + 'a {
+    let partial = partial.to_lowercase();
+
+    all_rewards()
+        .into_iter()
+        .map(|(_, item)| item)
+        .filter(move |item| item.to_lowercase().contains(&partial))
+        .take(25)
+}
+
 /// Print a help message
 #[command(slash_command, guild_cooldown = 360)]
 pub async fn help(ctx: Context<'_>) -> Result<()> {
     let help_message = "Available Commands:\n\
+                        - `/archon`: Show the Archon Hunt and the Archons coming up, optionally `weeks_ahead`\n\
+                        - `/archon_reminders`: Get told when the Archon Hunt drops a colour of shard\n\
                         - `/baro`  : Show when baro will be here next, or his inventory if he's here\n\
-                        - `/news`  : Show unseen news\n\
+                        - `/baro_reminders`: Opt in or out of a direct message before baro arrives\n\
+                        - `/calendar`: Get a calendar file of upcoming resets, rotations and baro's visits\n\
+                        - `/circuit rotation`: Show The Circuit's rotation, optionally `weeks_ahead`\n\
+                        - `/circuit find`: Find when The Circuit next offers a warframe or weapon\n\
+                        - `/circuit subscribe`: Get told on the weekly reset when The Circuit offers something\n\
+                        - `/circuit unsubscribe`, `/circuit subscriptions`: Manage what you are waiting for\n\
+                        - `/circuit reload`: Reload The Circuit's rotations (bot owners only)\n\
+                        - `/dashboard`: Post a dashboard of timers that is kept up to date (admins only)\n\
+                        - `/news`  : Show recent news, optionally with a `count` and a `since` date\n\
                         - `/help`  : Print this message\n\
                         - `/weekly`: Show what's in The Circuit and what Archon Hunt is available";
     ctx.say(help_message).await?;
@@ -0,0 +1,46 @@
+use crate::cache::{read_dump, write_dump};
+use crate::warning;
+
+use anyhow::Result;
+use poise::serenity_prelude::{ChannelId, MessageId};
+use serde::{Deserialize, Serialize};
+
+/// The message that the dashboard is shown in, which is edited in place with the current timers.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Dashboard {
+    #[serde(with = "crate::discord_id")]
+    channel_id: ChannelId,
+    #[serde(with = "crate::discord_id")]
+    message_id: MessageId,
+}
+
+impl Dashboard {
+    pub fn new(channel_id: ChannelId, message_id: MessageId) -> Self {
+        Self {
+            channel_id,
+            message_id,
+        }
+    }
+
+    /// Load the dashboard from its default location, if there is one.
+    pub fn load() -> Option<Self> {
+        read_dump::<Option<Self>>("dashboard.bin")
+            .inspect_err(|e| warning!(context = "loading dashboard", "{e}"))
+            .ok()
+            .flatten()
+            .flatten()
+    }
+
+    /// Dump the dashboard, or the lack of one, to the default location.
+    pub fn dump(dashboard: Option<Self>) -> Result<()> {
+        write_dump("dashboard.bin", &dashboard)
+    }
+
+    pub fn channel_id(&self) -> ChannelId {
+        self.channel_id
+    }
+
+    pub fn message_id(&self) -> MessageId {
+        self.message_id
+    }
+}
@@ -0,0 +1,73 @@
+//! Serde helpers for persisting Discord IDs, such as message and scheduled event IDs, with bincode.
+//!
+//! Serenity's IDs deserialise from either a string or an integer, which requires a
+//! self-describing format. These helpers store them as plain integers instead, and reject the
+//! zero that serenity would panic on.
+
+use std::num::NonZeroU64;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+pub fn serialize<S, Id>(id: &Id, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    Id: Copy + Into<NonZeroU64>,
+{
+    (*id).into().serialize(serializer)
+}
+
+pub fn deserialize<'de, D, Id>(deserializer: D) -> Result<Id, D::Error>
+where
+    D: Deserializer<'de>,
+    Id: From<NonZeroU64>,
+{
+    Ok(Id::from(NonZeroU64::deserialize(deserializer)?))
+}
+
+/// Helpers for optional IDs.
+pub mod option {
+    use super::*;
+
+    pub fn serialize<S, Id>(id: &Option<Id>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        Id: Copy + Into<NonZeroU64>,
+    {
+        id.map(Into::into).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D, Id>(deserializer: D) -> Result<Option<Id>, D::Error>
+    where
+        D: Deserializer<'de>,
+        Id: From<NonZeroU64>,
+    {
+        Ok(Option::<NonZeroU64>::deserialize(deserializer)?.map(Id::from))
+    }
+}
+
+#[cfg(test)]
+mod discord_id_test {
+    use poise::serenity_prelude::MessageId;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Posted(#[serde(with = "super")] MessageId);
+
+    #[test]
+    fn round_trip() {
+        let cfg = bincode::config::standard();
+        let posted = Posted(MessageId::new(42));
+
+        let dump = bincode::serde::encode_to_vec(&posted, cfg).unwrap();
+        let (loaded, _): (Posted, _) = bincode::serde::decode_from_slice(&dump, cfg).unwrap();
+        assert_eq!(loaded, posted);
+    }
+
+    #[test]
+    fn rejects_zero() {
+        let cfg = bincode::config::standard();
+
+        let dump = bincode::serde::encode_to_vec(0u64, cfg).unwrap();
+        assert!(bincode::serde::decode_from_slice::<Posted, _>(&dump, cfg).is_err());
+    }
+}
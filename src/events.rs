@@ -0,0 +1,237 @@
+use crate::News;
+use crate::item_display::{BaroInfo, WeeklyInfo};
+use crate::warning;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use tokio::task::JoinHandle;
+
+/// The header containing the signature of the payload, if a secret is configured.
+pub const SIGNATURE_HEADER: &str = "X-WF-Bot-Signature";
+
+/// How many times delivery to a single target is attempted before giving up.
+const MAX_ATTEMPTS: u32 = 4;
+
+/// A notification, as sent to the generic webhook targets.
+#[derive(Debug, Clone, Serialize)]
+pub struct Event {
+    #[serde(flatten)]
+    pub kind: EventKind,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", content = "payload", rename_all = "snake_case")]
+pub enum EventKind {
+    News(News),
+    Baro(BaroInfo),
+    Weekly(WeeklyInfo),
+}
+
+impl From<EventKind> for Event {
+    fn from(kind: EventKind) -> Self {
+        Self {
+            kind,
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+/// A list of generic HTTP webhooks that receive a JSON payload for every notification the bot
+/// sends.
+#[derive(Debug, Clone)]
+pub struct EventFeed {
+    targets: Vec<String>,
+    secret: Option<String>,
+    retry_delay: Duration,
+    client: reqwest::Client,
+    /// Deliveries that may still be running in the background.
+    deliveries: Arc<Mutex<Vec<JoinHandle<()>>>>,
+}
+
+impl EventFeed {
+    /// Create a feed for the given targets. If a secret is given, every payload is signed with
+    /// it.
+    pub fn new(targets: Vec<String>, secret: Option<String>) -> Self {
+        Self {
+            targets,
+            secret,
+            retry_delay: Duration::from_secs(1),
+            client: reqwest::Client::new(),
+            deliveries: Arc::default(),
+        }
+    }
+
+    /// Send the event to every target in the background, so that slow or failing targets do not
+    /// hold up the bot's own notifications. Failures are logged.
+    pub fn publish(&self, kind: EventKind) {
+        if self.targets.is_empty() {
+            return;
+        }
+
+        let body = match serde_json::to_vec(&Event::from(kind)) {
+            Ok(body) => body,
+            Err(e) => {
+                warning!(context = "serialising event", "{e}");
+                return;
+            }
+        };
+
+        let mut deliveries = self.deliveries.lock().unwrap_or_else(|e| e.into_inner());
+        deliveries.retain(|delivery| !delivery.is_finished());
+
+        for target in self.targets.iter() {
+            let target = target.clone();
+            let feed = self.clone();
+            let body = body.clone();
+
+            deliveries.push(tokio::spawn(async move {
+                if let Err(e) = feed.deliver(&target, &body).await {
+                    warning!(context = "publishing event", "{}: {e}", host(&target));
+                }
+            }));
+        }
+    }
+
+    /// Wait for the deliveries still running in the background, e.g., before the process exits.
+    pub async fn finish(&self) {
+        let deliveries =
+            std::mem::take(&mut *self.deliveries.lock().unwrap_or_else(|e| e.into_inner()));
+
+        for delivery in deliveries {
+            let _ = delivery.await;
+        }
+    }
+
+    /// Post the body to a single target, retrying with exponential backoff.
+    async fn deliver(&self, target: &str, body: &[u8]) -> Result<()> {
+        let mut delay = self.retry_delay;
+
+        for attempt in 1..MAX_ATTEMPTS {
+            let Err(error) = self.attempt(target, body).await else {
+                return Ok(());
+            };
+
+            warning!(
+                context = "publishing event",
+                "{}: attempt {attempt} failed, retrying in {delay:?}: {error}",
+                host(target)
+            );
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+
+        self.attempt(target, body)
+            .await
+            .map_err(|e| anyhow!("giving up after {MAX_ATTEMPTS} attempts: {e}"))
+    }
+
+    /// Post the body to a single target once.
+    async fn attempt(&self, target: &str, body: &[u8]) -> reqwest::Result<()> {
+        let mut request = self
+            .client
+            .post(target)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.to_vec());
+
+        if let Some(secret) = &self.secret {
+            request = request.header(SIGNATURE_HEADER, sign(secret, body));
+        }
+
+        // Targets may have secrets in their URLs, so errors leave them out.
+        request
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map(|_| ())
+            .map_err(reqwest::Error::without_url)
+    }
+}
+
+/// The host of a target, to name it in logs. The rest of its URL may contain secrets.
+fn host(target: &str) -> String {
+    reqwest::Url::parse(target)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| "invalid URL".to_string())
+}
+
+/// Sign the body using HMAC-SHA256, in the form `sha256=<hex digest>`.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    // HMAC accepts keys of any length.
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+    mac.update(body);
+
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod events_test {
+    use super::*;
+    use crate::test_util::stand_in;
+
+    #[test]
+    fn sign_known_value() {
+        // Test case 2 from RFC 4231.
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[tokio::test]
+    async fn deliver_retries() {
+        let (url, server) =
+            stand_in(&[("503 Service Unavailable", ""), ("204 No Content", "")]).await;
+
+        let mut feed = EventFeed::new(vec![url.clone()], Some("secret".into()));
+        feed.retry_delay = Duration::ZERO;
+
+        let body = br#"{"type":"news"}"#;
+        feed.deliver(&url, body).await.unwrap();
+
+        let requests = server.await.unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].contains(&sign("secret", body)));
+        assert!(requests[1].ends_with(r#"{"type":"news"}"#));
+    }
+
+    #[tokio::test]
+    async fn publish_in_background() {
+        let (url, server) = stand_in(&[("204 No Content", "")]).await;
+
+        let feed = EventFeed::new(vec![url], None);
+        feed.publish(EventKind::Weekly(WeeklyInfo {
+            archon_shard: "Crimson".to_string(),
+            normal_circuit: vec![],
+            sp_circuit: vec![],
+        }));
+        feed.finish().await;
+
+        assert!(server.await.unwrap()[0].contains(r#""type":"weekly""#));
+    }
+
+    #[test]
+    fn host_hides_path() {
+        assert_eq!(host("https://example.com/hooks/secret"), "example.com");
+        assert_eq!(host("not a url"), "invalid URL");
+    }
+
+    #[tokio::test]
+    async fn deliver_gives_up() {
+        let responses = [("500 Internal Server Error", ""); MAX_ATTEMPTS as usize];
+        let (url, server) = stand_in(&responses).await;
+
+        let mut feed = EventFeed::new(vec![url.clone()], None);
+        feed.retry_delay = Duration::ZERO;
+
+        assert!(feed.deliver(&url, b"{}").await.is_err());
+        assert_eq!(server.await.unwrap().len(), MAX_ATTEMPTS as usize);
+    }
+}
@@ -0,0 +1,365 @@
+use crate::{metrics, warning};
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use tokio::sync::Mutex;
+use warframe::worldstate::{Client, Queryable};
+
+/// How many times a scheduled job attempts a fetch before giving up.
+const MAX_ATTEMPTS: u32 = 4;
+
+/// How many fetches in a row must fail for the breaker to open.
+const BREAKER_THRESHOLD: u32 = 5;
+
+/// How long an open breaker turns fetches away before letting them try the worldstate again.
+const BREAKER_COOLDOWN: TimeDelta = TimeDelta::minutes(2);
+
+/// How long a snapshot is served as it is, rather than fetching the model again.
+const SNAPSHOT_FRESH_FOR: TimeDelta = TimeDelta::minutes(1);
+
+/// How old a snapshot can be before readers are told how old it is.
+const SNAPSHOT_STALE_AFTER: TimeDelta = TimeDelta::minutes(5);
+
+/// Why the worldstate could not be fetched.
+#[derive(Debug)]
+pub enum FetchError {
+    /// The worldstate could not be reached, or its response could not be understood.
+    Worldstate(warframe::worldstate::Error),
+    /// Too many fetches failed in a row, so the worldstate is left alone until the given time.
+    BreakerOpen(DateTime<Utc>),
+}
+
+impl Display for FetchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            FetchError::Worldstate(e) => write!(f, "{e}"),
+            FetchError::BreakerOpen(until) => write!(
+                f,
+                "the worldstate keeps failing, so it is not fetched from until {}",
+                until.format("%H:%M:%S UTC")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FetchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FetchError::Worldstate(e) => Some(e),
+            FetchError::BreakerOpen(_) => None,
+        }
+    }
+}
+
+/// How hard to try fetching before giving up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Attempts {
+    /// A single attempt, for slash commands and the HTTP endpoints, where someone is waiting.
+    Once,
+    /// Several attempts with backoff, for scheduled jobs that would otherwise miss their moment,
+    /// e.g., Baro Ki'Teer's arrival.
+    Retrying,
+}
+
+/// A model as it was when it was last fetched successfully.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot<T> {
+    pub value: T,
+    pub fetched_at: DateTime<Utc>,
+}
+
+impl<T> Snapshot<T> {
+    pub fn new(value: T, fetched_at: DateTime<Utc>) -> Self {
+        Self { value, fetched_at }
+    }
+
+    /// Apply a function to the value, keeping when it was fetched.
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Snapshot<U> {
+        Snapshot::new(f(self.value), self.fetched_at)
+    }
+
+    /// Whether the snapshot is recent enough to be served without fetching the model again.
+    pub fn is_fresh(&self, now: DateTime<Utc>) -> bool {
+        now - self.fetched_at < SNAPSHOT_FRESH_FOR
+    }
+
+    /// A note saying how old the snapshot is, if it is old enough that readers should know.
+    pub fn staleness_note(&self, now: DateTime<Utc>) -> Option<String> {
+        let age = now - self.fetched_at;
+
+        (age >= SNAPSHOT_STALE_AFTER).then(|| {
+            format!(
+                "*As of {} minutes ago, as the worldstate could not be fetched since.*",
+                age.num_minutes()
+            )
+        })
+    }
+}
+
+/// Stops fetching from the worldstate for a while once it keeps failing, rather than have every
+/// task hammer it in turn.
+#[derive(Debug, Clone, Default)]
+struct Breaker {
+    /// How many fetches in a row have failed.
+    failures: u32,
+    open_until: Option<DateTime<Utc>>,
+}
+
+impl Breaker {
+    /// Check whether a fetch may be attempted at the given time. Once the cooldown has passed,
+    /// fetches are let through again, and the first failure opens the breaker again.
+    fn check(&self, now: DateTime<Utc>) -> Result<(), FetchError> {
+        match self.open_until {
+            Some(until) if now < until => Err(FetchError::BreakerOpen(until)),
+            _ => Ok(()),
+        }
+    }
+
+    fn succeeded(&mut self) {
+        self.failures = 0;
+        self.open_until = None;
+    }
+
+    /// Record a failed fetch, returning `true` if the breaker opened because of it.
+    fn failed(&mut self, now: DateTime<Utc>) -> bool {
+        self.failures += 1;
+        if self.failures < BREAKER_THRESHOLD {
+            return false;
+        }
+
+        self.open_until = Some(now + BREAKER_COOLDOWN);
+        true
+    }
+}
+
+/// Fetches from the worldstate, retrying where asked to, through a breaker shared by every fetch.
+/// Keeps a snapshot of the last of each model fetched successfully.
+#[derive(Debug)]
+pub struct Fetcher {
+    client: Client,
+    breaker: Mutex<Breaker>,
+    retry_delay: Duration,
+    /// The latest snapshot of each model, by the model's type.
+    snapshots: std::sync::Mutex<HashMap<TypeId, Box<dyn Any + Send + Sync>>>,
+}
+
+impl Fetcher {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            breaker: Mutex::new(Breaker::default()),
+            retry_delay: Duration::from_secs(2),
+            snapshots: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The last of the model that was fetched successfully, if any has been.
+    pub fn snapshot<T>(&self) -> Option<Snapshot<T::Return>>
+    where
+        T: Queryable + 'static,
+        T::Return: Clone + Send + Sync,
+    {
+        let snapshots = self.snapshots.lock().unwrap_or_else(|e| e.into_inner());
+
+        snapshots
+            .get(&TypeId::of::<T>())
+            .and_then(|snapshot| snapshot.downcast_ref::<Snapshot<T::Return>>())
+            .cloned()
+    }
+
+    fn store<T>(&self, value: &T::Return)
+    where
+        T: Queryable + 'static,
+        T::Return: Clone + Send + Sync,
+    {
+        let snapshot = Snapshot::new(value.clone(), Utc::now());
+
+        self.snapshots
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(TypeId::of::<T>(), Box::new(snapshot));
+    }
+
+    /// Fetch a model from the worldstate, making as many attempts as asked for.
+    pub async fn fetch<T>(&self, attempts: Attempts) -> Result<T::Return, FetchError>
+    where
+        T: Queryable + 'static,
+        T::Return: Clone + Send + Sync,
+    {
+        let queryable = queryable_name::<T>();
+        let max_attempts = match attempts {
+            Attempts::Once => 1,
+            Attempts::Retrying => MAX_ATTEMPTS,
+        };
+
+        for attempt in 1..max_attempts {
+            let error = match self.attempt::<T>().await? {
+                Ok(fetched) => return Ok(fetched),
+                Err(e) => e,
+            };
+
+            let delay = backoff(self.retry_delay, attempt, jitter());
+            warning!(
+                context = "fetching worldstate",
+                "{queryable}: attempt {attempt} failed, retrying in {delay:?}: {error}"
+            );
+            tokio::time::sleep(delay).await;
+        }
+
+        self.attempt::<T>().await?.map_err(FetchError::Worldstate)
+    }
+
+    /// Make a single attempt at fetching a model, through the breaker. An open breaker is
+    /// returned as the outer error, as it is not worth retrying.
+    async fn attempt<T>(&self) -> Result<Result<T::Return, warframe::worldstate::Error>, FetchError>
+    where
+        T: Queryable + 'static,
+        T::Return: Clone + Send + Sync,
+    {
+        self.breaker.lock().await.check(Utc::now())?;
+
+        let started = Instant::now();
+        let fetched = self.client.fetch::<T>().await;
+        metrics::worldstate_fetched(queryable_name::<T>(), started.elapsed(), fetched.is_ok());
+
+        match &fetched {
+            Ok(fetched) => {
+                self.breaker.lock().await.succeeded();
+                self.store::<T>(fetched);
+            }
+            Err(_) => {
+                if self.breaker.lock().await.failed(Utc::now()) {
+                    warning!(
+                        context = "fetching worldstate",
+                        "{BREAKER_THRESHOLD} fetches in a row failed, pausing for {} minutes",
+                        BREAKER_COOLDOWN.num_minutes()
+                    );
+                }
+            }
+        }
+
+        Ok(fetched)
+    }
+}
+
+/// The name of a model in the worldstate, e.g., `VoidTrader`.
+pub fn queryable_name<T: Queryable>() -> &'static str {
+    std::any::type_name::<T>()
+        .rsplit("::")
+        .next()
+        .unwrap_or_default()
+}
+
+/// The delay after the given failed attempt. The delay doubles with every attempt, and the
+/// jitter, between 0 and 1, spreads it over the upper half so that tasks do not retry in step.
+fn backoff(base: Duration, attempt: u32, jitter: f64) -> Duration {
+    let delay = base * 2u32.pow(attempt.saturating_sub(1).min(16));
+
+    delay.mul_f64(0.5 + jitter / 2.0)
+}
+
+/// A number between 0 and 1 that is different enough from one call to the next to be used as
+/// jitter.
+fn jitter() -> f64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_nanos();
+
+    f64::from(nanos) / 1e9
+}
+
+#[cfg(test)]
+mod fetch_test {
+    use super::*;
+    use crate::test_util::stand_in;
+
+    use warframe::worldstate::queryable;
+
+    #[test]
+    fn backoff_doubles_with_jitter() {
+        let base = Duration::from_secs(2);
+
+        assert_eq!(backoff(base, 1, 1.0), Duration::from_secs(2));
+        assert_eq!(backoff(base, 2, 1.0), Duration::from_secs(4));
+        assert_eq!(backoff(base, 3, 0.0), Duration::from_secs(4));
+        assert!(backoff(base, 3, jitter()) >= Duration::from_secs(4));
+    }
+
+    #[test]
+    fn breaker_opens_and_recovers() {
+        let mut breaker = Breaker::default();
+        let now = Utc::now();
+
+        for _ in 1..BREAKER_THRESHOLD {
+            assert!(!breaker.failed(now));
+        }
+        assert!(breaker.failed(now));
+        assert!(matches!(
+            breaker.check(now),
+            Err(FetchError::BreakerOpen(_))
+        ));
+
+        // After the cooldown a fetch is let through, and a single failure opens it again.
+        let later = now + BREAKER_COOLDOWN;
+        assert!(breaker.check(later).is_ok());
+        assert!(breaker.failed(later));
+
+        breaker.succeeded();
+        assert!(breaker.check(later).is_ok());
+    }
+
+    #[tokio::test]
+    async fn fetch_retries() {
+        let (url, server) = stand_in(&[("500 Internal Server Error", ""), ("200 OK", "[]")]).await;
+        let mut fetcher = Fetcher::new(Client::new(reqwest::Client::new(), url));
+        fetcher.retry_delay = Duration::ZERO;
+
+        let news = fetcher
+            .fetch::<queryable::News>(Attempts::Retrying)
+            .await
+            .unwrap();
+
+        assert!(news.is_empty());
+        assert_eq!(server.await.unwrap().len(), 2);
+
+        let snapshot = fetcher.snapshot::<queryable::News>().unwrap();
+        assert!(snapshot.value.is_empty());
+        assert!(fetcher.snapshot::<queryable::VoidTrader>().is_none());
+    }
+
+    #[tokio::test]
+    async fn fetch_gives_up() {
+        let responses = [("500 Internal Server Error", ""); MAX_ATTEMPTS as usize];
+        let (url, server) = stand_in(&responses).await;
+        let mut fetcher = Fetcher::new(Client::new(reqwest::Client::new(), url));
+        fetcher.retry_delay = Duration::ZERO;
+
+        let result = fetcher.fetch::<queryable::News>(Attempts::Retrying).await;
+
+        assert!(matches!(result, Err(FetchError::Worldstate(_))));
+        assert_eq!(server.await.unwrap().len(), MAX_ATTEMPTS as usize);
+    }
+
+    #[test]
+    fn staleness() {
+        let now = Utc::now();
+
+        let snapshot = Snapshot::new((), now - TimeDelta::seconds(30));
+        assert!(snapshot.is_fresh(now));
+        assert_eq!(snapshot.staleness_note(now), None);
+
+        let snapshot = Snapshot::new((), now - TimeDelta::minutes(12));
+        assert!(!snapshot.is_fresh(now));
+        assert!(
+            snapshot
+                .staleness_note(now)
+                .unwrap()
+                .contains("12 minutes ago")
+        );
+    }
+}
@@ -1,156 +1,1242 @@
-use crate::News;
+use crate::alerts::{AlertDestination, Alerts, Problem};
+use crate::archon::{Archon, archon_message};
+use crate::baro::{
+    ArrivalReminder, BaroVisit, PostedMessage, arrival_message, arrival_reminder_due,
+};
 use crate::cache::SeenCache;
-use crate::item_display::{WeeklyInfo, calculate_baro_string};
+use crate::calendar::calendar;
+use crate::circuit::{DuviriCycle, LiveChoices};
+use crate::dashboard::Dashboard;
+use crate::events::{EventFeed, EventKind};
+use crate::fetch::{Attempts, FetchError, Fetcher, Snapshot, queryable_name};
+use crate::item_display::{BaroInfo, CycleInfo, DashboardInfo, WeeklyInfo, calculate_baro_string};
+use crate::metrics;
+use crate::news_wrapper::PostedNews;
+use crate::outbox::{Failure, Outbox, Subject};
+use crate::scheduled_events::{EventDetails, ScheduledEvents, SyncAction, SyncedEvent};
+use crate::subscribers::{ItemSubscribers, ItemSubscription, Subscribers};
+use crate::webhook::Webhook;
+use crate::{DateStyle, MESSAGE_LIMIT, News, join_lines};
 use crate::{error, info, warning};
 
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
 use std::sync::Arc;
 
-use poise::serenity_prelude::{ChannelId, Http};
+use anyhow::{Result, anyhow, bail};
+use chrono::{DateTime, TimeDelta, Timelike, Utc};
+use itertools::Itertools;
+use poise::serenity_prelude::{
+    self as serenity, ActivityData, ChannelId, ChannelType, ConnectionStage, CreateMessage,
+    EditChannel, EditMessage, FullEvent, GuildId, Http, Mentionable, MessageId, ShardMessenger,
+    StatusCode, UserId,
+};
 use tokio::sync::Mutex;
 use warframe::worldstate::client::Client;
-use warframe::worldstate::{TimedEvent, queryable};
+use warframe::worldstate::queryable::{self, ArchonHunt, VoidTrader};
+use warframe::worldstate::{Queryable, TimedEvent};
+
+/// How many times in a row editing a post may fail before it is no longer edited.
+const MAX_EDIT_ATTEMPTS: u32 = 10;
+
+/// Where the bot's notifications are delivered.
+#[derive(Debug, Clone)]
+pub enum Destination {
+    /// A channel, posted to through the bot's connection.
+    Channel(ChannelId),
+    /// One or more webhooks, posted to without a connection to Discord.
+    Webhooks(Vec<Webhook>),
+}
+
+impl Destination {
+    /// Get each of the individual targets that messages are delivered to.
+    pub fn targets(&self) -> Vec<Target> {
+        match self {
+            Destination::Channel(channel_id) => vec![Target::Channel(*channel_id)],
+            Destination::Webhooks(webhooks) => {
+                webhooks.iter().cloned().map(Target::Webhook).collect()
+            }
+        }
+    }
+}
+
+/// A single place that messages are delivered to.
+#[derive(Debug, Clone)]
+pub enum Target {
+    Channel(ChannelId),
+    Webhook(Webhook),
+}
+
+impl Target {
+    /// A stable identifier for the target, which is safe to persist.
+    pub fn key(&self) -> String {
+        match self {
+            Target::Channel(channel_id) => format!("channel-{channel_id}"),
+            Target::Webhook(webhook) => webhook.key(),
+        }
+    }
+}
+
+/// A list of items users can wait for, to be told on the weekly reset when they are offered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wishlist {
+    /// The Circuit's warframes and incarnon weapons.
+    Circuit,
+    /// The colours of shard dropped by the Archon Hunt.
+    ArchonShard,
+}
+
+impl Wishlist {
+    /// The direct message telling a subscriber that the given items are offered this week.
+    fn message(self, items: &str) -> String {
+        match self {
+            Wishlist::Circuit => format!("The Circuit offers {items} this week."),
+            Wishlist::ArchonShard => format!("The Archon Hunt drops the {items} this week."),
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Handler {
-    channel_id: ChannelId,
+    destination: Option<Destination>,
     connection: Arc<Mutex<Option<Arc<Http>>>>,
-    news_cache: Arc<Mutex<SeenCache<News, 20>>>,
-    worldstate: Client,
+    /// The gateway shard, used to set the bot's presence.
+    shard: Arc<Mutex<Option<ShardMessenger>>>,
+    /// Whether the gateway shard is currently connected, as last reported by the gateway.
+    gateway_connected: Arc<Mutex<bool>>,
+    /// The news seen by each target, and the messages it was posted as, by target key. Loaded
+    /// when first needed.
+    news_caches: Arc<Mutex<HashMap<String, SeenCache<PostedNews, 20>>>>,
+    /// How many times in a row editing each post has failed, by target key and news item ID.
+    edit_failures: Arc<Mutex<HashMap<(String, String), u32>>>,
+    outbox: Arc<Mutex<Outbox>>,
+    /// Held while the outbox is flushed, so that no message is sent twice.
+    flushing: Arc<Mutex<()>>,
+    /// The last visit from Baro Ki'Teer whose inventory was posted.
+    baro_visit: Arc<Mutex<Option<BaroVisit>>>,
+    /// The arrival reminders that have been sent.
+    sent_reminders: Arc<Mutex<SeenCache<ArrivalReminder, 10>>>,
+    /// Users that get a direct message with every arrival reminder.
+    baro_subscribers: Arc<Mutex<Subscribers>>,
+    /// Users waiting for The Circuit to offer particular rewards.
+    circuit_subscribers: Arc<Mutex<ItemSubscribers>>,
+    /// Users waiting for the Archon Hunt to drop particular colours of shard.
+    shard_subscribers: Arc<Mutex<ItemSubscribers>>,
+    /// The guild scheduled events created by the bot.
+    scheduled_events: Arc<Mutex<ScheduledEvents>>,
+    /// The message showing the dashboard, if one was posted.
+    dashboard: Arc<Mutex<Option<Dashboard>>>,
+    /// The dashboard's timers as last fetched, shared by everything that shows them.
+    dashboard_info: Arc<Mutex<Option<Snapshot<DashboardInfo>>>>,
+    events: Option<EventFeed>,
+    /// How many of the most recent news items to post when a target has not seen any news yet.
+    backfill: usize,
+    /// How long before Baro Ki'Teer leaves to remind the targets, if at all.
+    leaving_reminder: Option<TimeDelta>,
+    /// How many hours before Baro Ki'Teer arrives to remind the targets.
+    arrival_reminders: Vec<u32>,
+    /// The guild to keep scheduled events for Baro Ki'Teer's visits in, if any.
+    event_guild: Option<GuildId>,
+    /// Whether to also keep a scheduled event for the weekly reset.
+    weekly_event: bool,
+    /// The channel whose topic, or name for voice channels, shows the current timers.
+    timer_channel: Option<ChannelId>,
+    /// The text last shown in the timer channel, to avoid needless edits.
+    timer_text: Arc<Mutex<Option<String>>>,
+    /// When the worldstate was last fetched successfully.
+    last_fetch: Arc<Mutex<Option<DateTime<Utc>>>>,
+    /// Where to alert the operators about persistent problems, if anywhere.
+    alert_destination: Option<AlertDestination>,
+    alerts: Arc<Mutex<Alerts>>,
+    worldstate: Arc<Fetcher>,
 }
 
 impl Handler {
-    pub fn new(channel_id: ChannelId) -> Self {
+    /// Create a new handler. Without a destination the handler can only be used to fetch
+    /// information.
+    pub fn new(destination: Option<Destination>) -> Self {
         Self {
-            channel_id,
+            destination,
             connection: Arc::new(Mutex::new(None)),
-            news_cache: Arc::new(Mutex::new(SeenCache::new("NEWS"))),
-            worldstate: Client::default(),
+            shard: Arc::new(Mutex::new(None)),
+            gateway_connected: Arc::new(Mutex::new(false)),
+            news_caches: Arc::new(Mutex::new(HashMap::new())),
+            edit_failures: Arc::new(Mutex::new(HashMap::new())),
+            outbox: Arc::new(Mutex::new(Outbox::new())),
+            flushing: Arc::new(Mutex::new(())),
+            baro_visit: Arc::new(Mutex::new(BaroVisit::load())),
+            sent_reminders: Arc::new(Mutex::new(SeenCache::new("BARO_REMINDERS"))),
+            baro_subscribers: Arc::new(Mutex::new(Subscribers::new("baro"))),
+            circuit_subscribers: Arc::new(Mutex::new(ItemSubscribers::new("circuit"))),
+            shard_subscribers: Arc::new(Mutex::new(ItemSubscribers::new("archon_shards"))),
+            scheduled_events: Arc::new(Mutex::new(ScheduledEvents::new())),
+            dashboard: Arc::new(Mutex::new(Dashboard::load())),
+            dashboard_info: Arc::new(Mutex::new(None)),
+            events: None,
+            backfill: 0,
+            leaving_reminder: None,
+            arrival_reminders: vec![],
+            event_guild: None,
+            weekly_event: false,
+            timer_channel: None,
+            timer_text: Arc::new(Mutex::new(None)),
+            last_fetch: Arc::new(Mutex::new(None)),
+            alert_destination: None,
+            alerts: Arc::new(Mutex::new(Alerts::new())),
+            worldstate: Arc::new(Fetcher::new(Client::default())),
         }
     }
 
+    /// Additionally publish every notification to the given event feed.
+    pub fn with_event_feed(mut self, events: EventFeed) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Post the given number of the most recent news items to targets that have not seen any news
+    /// yet, rather than none.
+    pub fn with_backfill(mut self, backfill: usize) -> Self {
+        self.backfill = backfill;
+        self
+    }
+
+    /// Remind the targets that Baro Ki'Teer is leaving the given amount of time before he does.
+    pub fn with_leaving_reminder(mut self, before: Option<TimeDelta>) -> Self {
+        self.leaving_reminder = before;
+        self
+    }
+
+    /// Remind the targets the given numbers of hours before Baro Ki'Teer arrives.
+    pub fn with_arrival_reminders(mut self, hours: Vec<u32>) -> Self {
+        self.arrival_reminders = hours;
+        self
+    }
+
+    /// Keep scheduled events for Baro Ki'Teer's visits, and optionally the weekly reset, in the
+    /// given guild.
+    pub fn with_scheduled_events(mut self, guild: Option<GuildId>, weekly: bool) -> Self {
+        self.event_guild = guild;
+        self.weekly_event = weekly;
+        self
+    }
+
+    /// Show the current timers in the given channel's topic, or its name for voice channels.
+    pub fn with_timer_channel(mut self, channel: Option<ChannelId>) -> Self {
+        self.timer_channel = channel;
+        self
+    }
+
+    /// Alert the given channel or user about persistent problems, such as repeated failures to
+    /// fetch the worldstate, and tell them once each problem clears.
+    pub fn with_alerts(mut self, destination: Option<AlertDestination>) -> Self {
+        self.alert_destination = destination;
+        self
+    }
+
     /// Initialise the connection to the Discord Client.
     pub async fn init_connection(&self, connection: Arc<Http>) {
         *self.connection.lock().await = Some(connection);
     }
 
-    /// Returns a list of news items as messages. Empty if no news items were found.
-    pub async fn news_messages(&self) -> Vec<String> {
-        // Fetch the recent news, and map it into the correct type
-        let news: Vec<News> = match self.worldstate.fetch::<queryable::News>().await {
-            Ok(response) => response
-                .into_iter()
-                .filter_map(|news_item| {
-                    let mapped = News::from(news_item);
+    /// Initialise the gateway shard, which the bot's presence is set through.
+    pub async fn init_shard(&self, shard: ShardMessenger) {
+        *self.shard.lock().await = Some(shard);
+    }
+
+    /// Keep track of whether the gateway is connected, from the events it sends.
+    pub async fn gateway_event(&self, event: &FullEvent) {
+        let connected = match event {
+            FullEvent::Ready { .. } | FullEvent::Resume { .. } => true,
+            FullEvent::ShardStageUpdate { event } => event.new == ConnectionStage::Connected,
+            _ => return,
+        };
+
+        *self.gateway_connected.lock().await = connected;
+    }
+
+    /// Use the given worldstate client, e.g., to fetch from a stand-in in tests.
+    #[cfg(test)]
+    pub fn with_worldstate(mut self, worldstate: Client) -> Self {
+        self.worldstate = Arc::new(Fetcher::new(worldstate));
+        self
+    }
+
+    /// Returns whether the bot's gateway connection to Discord is up, or `None` if it needs no
+    /// connection, e.g., because it delivers through webhooks.
+    pub async fn discord_connected(&self) -> Option<bool> {
+        match self.destination {
+            Some(Destination::Channel(_)) => Some(*self.gateway_connected.lock().await),
+            _ => None,
+        }
+    }
 
-                    // Ignore news that is alway active, e.g., "Join the Warframe Discord!"
-                    white_listed(&mapped).then_some(mapped)
-                })
-                .collect(),
+    /// When the worldstate was last fetched successfully, if it has been.
+    pub async fn last_fetch(&self) -> Option<DateTime<Utc>> {
+        *self.last_fetch.lock().await
+    }
+
+    /// Fetches a model from the worldstate, logging any failure under the given context.
+    async fn fetch<T>(&self, attempts: Attempts, context: &str) -> Result<T::Return, FetchError>
+    where
+        T: Queryable + 'static,
+        T::Return: Clone + Send + Sync,
+    {
+        let problem = Problem::Fetch(queryable_name::<T>().to_string());
 
+        match self.worldstate.fetch::<T>(attempts).await {
+            Ok(fetched) => {
+                *self.last_fetch.lock().await = Some(Utc::now());
+                self.problem_cleared(problem).await;
+                Ok(fetched)
+            }
             Err(e) => {
-                warning!(context = "fetching news", "{e}");
-                return vec![];
+                warning!(context = context, "{e}");
+                // An open breaker is the result of failures that have already been counted.
+                if let FetchError::Worldstate(_) = e {
+                    self.problem_happened(problem, &e).await;
+                }
+                Err(e)
             }
-        };
+        }
+    }
+
+    /// Returns the snapshot of a model, fetching it first unless the snapshot is fresh. If the
+    /// fetch fails, the last snapshot is returned instead, however old it is, so that commands
+    /// can still be answered while the worldstate is down.
+    async fn latest<T>(&self, context: &str) -> Result<Snapshot<T::Return>, FetchError>
+    where
+        T: Queryable + 'static,
+        T::Return: Clone + Send + Sync,
+    {
+        let snapshot = self.worldstate.snapshot::<T>();
+        if let Some(snapshot) = snapshot.filter(|snapshot| snapshot.is_fresh(Utc::now())) {
+            return Ok(snapshot);
+        }
+
+        match self.fetch::<T>(Attempts::Once, context).await {
+            Ok(fetched) => Ok(Snapshot::new(fetched, Utc::now())),
+            Err(e) => self.worldstate.snapshot::<T>().ok_or(e),
+        }
+    }
+
+    /// Fetch the models that commands and the dashboard are answered from, unless their
+    /// snapshots are fresh.
+    pub async fn refresh_snapshots(&self) {
+        // Failures are logged when fetching, and the old snapshots are kept.
+        let _ = tokio::join!(
+            self.latest::<queryable::News>("fetching news"),
+            self.latest::<queryable::VoidTrader>("fetching trader"),
+            self.latest::<queryable::ArchonHunt>("fetching archon"),
+            self.latest::<queryable::Cetus>("fetching cetus"),
+            self.latest::<queryable::OrbVallis>("fetching orb vallis"),
+            self.latest::<queryable::CambionDrift>("fetching cambion drift"),
+        );
+    }
+
+    /// Fetches the recent news, ignoring blacklisted items.
+    pub async fn recent_news(&self, attempts: Attempts) -> Result<Vec<News>, FetchError> {
+        let news = self
+            .fetch::<queryable::News>(attempts, "fetching news")
+            .await?;
+
+        Ok(white_listed_news(news))
+    }
+
+    /// Returns up to `count` of the most recent news items, optionally only those posted since
+    /// the given date, oldest first. Read from the snapshot of the news.
+    ///
+    /// This does not mark anything as seen, so it is safe for interactive use.
+    pub async fn latest_news(
+        &self,
+        count: usize,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<Snapshot<Vec<News>>, FetchError> {
+        let snapshot = self.latest::<queryable::News>("fetching news").await?;
+
+        Ok(snapshot.map(|news| {
+            let mut news: Vec<News> = white_listed_news(news)
+                .into_iter()
+                .filter(|news_item| since.is_none_or(|since| news_item.date >= since))
+                .sorted_by(|a, b| b.date.cmp(&a.date))
+                .take(count)
+                .collect();
+            news.reverse();
+
+            news
+        }))
+    }
+
+    /// Send messages to each target with the news items it has not seen thus far. News items are
+    /// marked as seen by a target once they have been delivered to it. Posts of news items that
+    /// have since changed or expired are edited to match.
+    pub async fn notify_news(&self) -> Result<()> {
+        let news = self
+            .recent_news(Attempts::Retrying)
+            .await?
+            .into_iter()
+            .sorted_by(|a, b| a.date.cmp(&b.date))
+            .collect::<Vec<_>>();
+        let targets = self.targets()?;
+
+        self.update_posts(&targets, &news).await;
+
+        // Get a handle for the outbox and the caches, in that order.
+        let mut outbox = self.outbox.lock().await;
+        let mut caches = self.news_caches.lock().await;
 
-        // Get a handle for the cache.
-        let mut cache = self.news_cache.lock().await;
+        // Queue the news listings that each target has not seen, and is not waiting for.
+        let mut queued = vec![];
+        for target in targets.iter() {
+            let key = target.key();
+            let cache = seen_cache(&mut caches, &key);
 
-        // Get the subset of news listings that have not been seen thus far.
-        let news = cache.difference(&news);
+            // On the first run, or if the cache was lost, don't flood the target with old news.
+            if cache.is_empty() {
+                let unposted = news
+                    .iter()
+                    .map(|news_item| PostedNews::new(news_item.clone(), None))
+                    .collect::<Vec<_>>();
+                let seeded = cache.seed(&unposted, self.backfill);
+                info!("marked {seeded} news items as seen for new target {key}");
 
-        // If there's nothing to report, we log it and move on.
-        if news.is_empty() {
-            return vec![];
+                self.dumped("news cache", cache.dump()).await;
+            }
+
+            for news_item in news.iter() {
+                if cache.contains(news_item) || outbox.contains_news(&key, news_item) {
+                    continue;
+                }
+
+                let message = news_item.as_message(DateStyle::Discord);
+                let subject = Subject::News(news_item.clone());
+                outbox.push(&key, message, Some(subject));
+
+                if !queued.contains(news_item) {
+                    queued.push(news_item.clone());
+                }
+            }
         }
 
-        // Update the cache.
-        if let Err(e) = cache.dump() {
-            warning!(context = "dumping cache", "{e}");
+        self.dumped("outbox", outbox.dump()).await;
+        drop(caches);
+        drop(outbox);
+
+        if queued.is_empty() {
+            info!("no unseen news");
+        }
+        for news_item in queued {
+            self.publish(EventKind::News(news_item));
         }
 
-        // If there are news items, map them into messages for consumption by other functions.
-        news.into_iter()
-            .filter_map(|news_item| {
-                news_item
-                    .as_message()
-                    .inspect_err(|e| warning!(context = "formatting news", "{e}"))
-                    .ok()
-            })
-            .collect::<Vec<_>>()
+        self.flush_outbox().await
     }
 
-    /// Send messages to the news channel with currently unseen news items.
-    pub async fn notify_news(&self) {
-        let messages = self.news_messages().await;
-        if messages.is_empty() {
-            info!("no unseen news");
+    /// Edit the posts of news items whose message or link has changed, or that have expired,
+    /// since they were posted to each target.
+    async fn update_posts(&self, targets: &[Target], news: &[News]) {
+        let now = Utc::now();
+
+        // Gather the edits, then make them without holding the caches, which other tasks need in
+        // the meantime.
+        let mut edits = vec![];
+        {
+            let mut caches = self.news_caches.lock().await;
+            for target in targets {
+                let key = target.key();
+                let cache = seen_cache(&mut caches, &key);
+
+                for post in cache.iter() {
+                    let latest = news.iter().find(|news_item| **news_item == post.news);
+                    // Posts without a message are never updated.
+                    if let Some(post) = post.updated(latest, now)
+                        && let Some(message_id) = post.message_id
+                    {
+                        edits.push((target, key.clone(), message_id, post));
+                    }
+                }
+            }
+        }
+        if edits.is_empty() {
             return;
         }
 
-        self.say_multiple(&messages).await;
+        let mut results = vec![];
+        for (target, key, message_id, post) in edits {
+            let result = self.edit(target, message_id, &post.as_message()).await;
+            results.push((key, post, result));
+        }
+
+        // Record the results, then dump the caches that changed once they are released.
+        let mut caches = self.news_caches.lock().await;
+        let mut failures = self.edit_failures.lock().await;
+        let mut changed = HashSet::new();
+        for (key, post, result) in results {
+            let cache = seen_cache(&mut caches, &key);
+            let failure = (key.clone(), post.news.id.clone());
+            match result {
+                Ok(()) => {
+                    info!("updated post of news item {} for {key}", post.news.id);
+                    failures.remove(&failure);
+                    if cache.replace(post) {
+                        changed.insert(key);
+                    }
+                }
+                Err(e) => {
+                    warning!(context = "editing news post", "{key}: {e}");
+
+                    // Forget the message of a post that can no longer be edited, so that it is
+                    // not attempted again.
+                    let attempts = failures.entry(failure.clone()).or_default();
+                    *attempts += 1;
+                    if is_gone(&e) || *attempts >= MAX_EDIT_ATTEMPTS {
+                        info!("no longer editing post of news item {}", post.news.id);
+                        failures.remove(&failure);
+                        if cache.replace(PostedNews::new(post.news, None)) {
+                            changed.insert(key);
+                        }
+                    }
+                }
+            }
+        }
+        let dumps: Vec<_> = changed
+            .iter()
+            .map(|key| seen_cache(&mut caches, key).dump())
+            .collect();
+        drop(failures);
+        drop(caches);
+
+        for result in dumps {
+            self.dumped("news cache", result).await;
+        }
     }
 
-    /// Returns `true` if Baro Ki'Teer is active.
-    pub async fn check_baro(&self) -> bool {
-        let trader = match self.worldstate.fetch::<queryable::VoidTrader>().await {
-            Ok(trader) => trader,
-            Err(e) => {
-                warning!(context = "fetching trader", "{e}");
-                return false;
+    /// Fetches the most recent information about Baro Ki'Teer.
+    pub async fn trader(&self, attempts: Attempts) -> Result<VoidTrader, FetchError> {
+        self.fetch::<queryable::VoidTrader>(attempts, "fetching trader")
+            .await
+    }
+
+    /// Returns `true` if Baro Ki'Teer is active. Retries, as this decides whether his inventory
+    /// is posted.
+    pub async fn check_baro(&self) -> Result<bool, FetchError> {
+        Ok(self.trader(Attempts::Retrying).await?.active())
+    }
+
+    /// Returns the snapshot of Baro Ki'Teer, for answering commands.
+    pub async fn latest_trader(&self) -> Result<Snapshot<VoidTrader>, FetchError> {
+        self.latest::<queryable::VoidTrader>("fetching trader")
+            .await
+    }
+
+    /// Fetches the Circuit's current offerings. `None` if the worldstate does not report them.
+    pub async fn circuit_choices(&self) -> Result<Option<Vec<LiveChoices>>, FetchError> {
+        let cycle = self
+            .fetch::<DuviriCycle>(Attempts::Once, "fetching duviri cycle")
+            .await?;
+
+        Ok((!cycle.choices.is_empty()).then_some(cycle.choices))
+    }
+
+    /// Utility function that reads the snapshot of the trader and passes it along to the
+    /// formatter function, noting how old it is if the worldstate could not be fetched lately.
+    pub async fn baro_messages(&self) -> Result<Vec<String>, FetchError> {
+        let snapshot = self.latest_trader().await?;
+
+        // Construct the messages
+        let mut messages = calculate_baro_string(&snapshot.value, DateStyle::Discord).await;
+        if let Some(first) = messages.first_mut() {
+            *first = with_staleness_note(std::mem::take(first), &snapshot);
+        }
+
+        Ok(messages)
+    }
+
+    /// Send a message or messages to the news channel with information about Baro Ki'Teer's
+    /// current or next visit.
+    pub async fn notify_baro(&self) -> Result<()> {
+        let trader = self.trader(Attempts::Retrying).await?;
+
+        // Remember the visit, so that the posts can be updated once he leaves.
+        let subject = match trader.active() {
+            true => {
+                let mut visit = self.baro_visit.lock().await;
+                if visit
+                    .as_ref()
+                    .is_none_or(|visit| visit.expiry != trader.expiry())
+                {
+                    let new_visit = BaroVisit::new(&trader);
+                    self.dumped("baro visit", new_visit.dump()).await;
+                    *visit = Some(new_visit);
+                }
+
+                Some(Subject::BaroVisit(trader.expiry()))
             }
+            false => None,
         };
 
-        trader.active()
+        let messages = calculate_baro_string(&trader, DateStyle::Discord).await;
+        self.enqueue(&messages, subject).await?;
+        self.publish(EventKind::Baro(BaroInfo::from(&trader)));
+
+        self.flush_outbox().await
     }
 
-    /// Utility function that fetches the active trader information and passes it along to the
-    /// formatter function.
-    pub async fn baro_messages(&self) -> Vec<String> {
-        // Fetch the most recent information about Baro Ki'Teer.
-        let trader = match self.worldstate.fetch::<queryable::VoidTrader>().await {
-            Ok(trader) => trader,
-            Err(e) => {
-                warning!(context = "fetching trader", "{e}");
-                return vec![];
+    /// Returns `true` if the inventory of the given visit of Baro Ki'Teer was already posted.
+    pub async fn baro_visit_posted(&self, trader: &VoidTrader) -> bool {
+        self.baro_visit
+            .lock()
+            .await
+            .as_ref()
+            .is_some_and(|visit| visit.expiry == trader.expiry())
+    }
+
+    /// Remind the targets, and the users that opted in, that Baro Ki'Teer is arriving soon, if
+    /// one of the configured reminders is due.
+    pub async fn notify_baro_arrival(&self) -> Result<()> {
+        if self.arrival_reminders.is_empty() {
+            return Ok(());
+        }
+
+        let trader = self.trader(Attempts::Retrying).await?;
+        let Some(hours) =
+            arrival_reminder_due(trader.activation(), Utc::now(), &self.arrival_reminders)
+        else {
+            return Ok(());
+        };
+
+        let reminder = (trader.activation(), hours);
+        if self.sent_reminders.lock().await.contains(&reminder) {
+            return Ok(());
+        }
+
+        let message = arrival_message(&trader.location, trader.activation());
+        self.enqueue(std::slice::from_ref(&message), None).await?;
+
+        let mut sent_reminders = self.sent_reminders.lock().await;
+        sent_reminders.insert(reminder);
+        self.dumped("reminder cache", sent_reminders.dump()).await;
+        drop(sent_reminders);
+
+        self.direct_message_subscribers(&message).await;
+
+        self.flush_outbox().await
+    }
+
+    /// Returns `true` if reminders are sent before Baro Ki'Teer arrives.
+    pub fn reminds_of_baro_arrival(&self) -> bool {
+        !self.arrival_reminders.is_empty()
+    }
+
+    /// Opt a user in or out of direct messages reminding them that Baro Ki'Teer is arriving.
+    ///
+    /// Returns `false` if nothing changed.
+    pub async fn set_baro_reminders(&self, user: UserId, enabled: bool) -> bool {
+        let mut subscribers = self.baro_subscribers.lock().await;
+        let changed = match enabled {
+            true => subscribers.subscribe(user),
+            false => subscribers.unsubscribe(user),
+        };
+
+        if changed {
+            self.dumped("subscribers", subscribers.dump()).await;
+        }
+
+        changed
+    }
+
+    /// Send a direct message to every user opted in to Baro Ki'Teer's reminders.
+    async fn direct_message_subscribers(&self, content: &str) {
+        let messages = self
+            .baro_subscribers
+            .lock()
+            .await
+            .users()
+            .map(|user| (user, content.to_string()))
+            .collect::<Vec<_>>();
+
+        self.direct_message(messages).await;
+    }
+
+    /// Send each user their direct message. Failures are logged, as a user may have left or
+    /// closed their direct messages.
+    async fn direct_message(&self, messages: Vec<(UserId, String)>) {
+        // Direct messages can only be sent through the bot's connection.
+        let Some(connection) = self.connection.lock().await.clone() else {
+            return;
+        };
+
+        for (user, content) in messages {
+            let message = CreateMessage::new().content(content);
+            let result = user.direct_message(&connection, message).await;
+            metrics::message_delivered(result.is_ok());
+
+            if let Err(e) = result {
+                warning!(context = "sending direct message", "{user}: {e}");
             }
+        }
+    }
+
+    /// Remind the targets shortly before Baro Ki'Teer leaves, if configured, and edit the posts
+    /// of his inventory once he has left to say when and where he will be next.
+    pub async fn update_baro_posts(&self) -> Result<()> {
+        let now = Utc::now();
+        let Some(visit) = self.baro_visit.lock().await.clone() else {
+            return Ok(());
         };
 
-        // Construct the messages
-        calculate_baro_string(&trader).await
+        if let Some(before) = self.leaving_reminder
+            && visit.reminder_due(now, before)
+        {
+            self.enqueue(&[visit.reminder_message()], None).await?;
+            self.update_baro_visit(|visit| visit.reminded = true).await;
+            self.flush_outbox().await?;
+        }
+
+        if !visit.has_left(now) {
+            return Ok(());
+        }
+
+        let next = self.trader(Attempts::Retrying).await?;
+
+        // The worldstate may take a moment to move on to his next visit.
+        if next.activation() < visit.expiry {
+            return Ok(());
+        }
+
+        let targets: HashMap<String, Target> = self
+            .targets()?
+            .into_iter()
+            .map(|target| (target.key(), target))
+            .collect();
+
+        let departure = visit.departure_message(&next);
+        for (PostedMessage { target, id }, content) in visit.departure_edits(&departure) {
+            let result = match targets.get(&target) {
+                Some(target) => self.edit(target, id, &content).await,
+                None => Err(anyhow!("target is no longer configured")),
+            };
+
+            if let Err(e) = result {
+                warning!(context = "editing baro post", "{target}: {e}");
+            }
+        }
+
+        // Failed edits are not retried, as the posts would only grow more out of date.
+        self.update_baro_visit(|visit| visit.departed = true).await;
+        info!("updated baro posts after his departure");
+
+        Ok(())
     }
 
-    /// Send a message or messages to the news channel with information about Baro Ki'Teer's
-    /// current or next visit.
-    pub async fn notify_baro(&self) {
-        let messages = self.baro_messages().await;
+    /// Create or update the guild scheduled events for Baro Ki'Teer's next visit and, if
+    /// configured, the next weekly reset, so that they match the worldstate.
+    pub async fn sync_scheduled_events(&self) -> Result<()> {
+        let Some(guild) = self.event_guild else {
+            return Ok(());
+        };
+
+        let now = Utc::now();
+        let mut events = self.scheduled_events.lock().await;
+        let mut failed = false;
+
+        match self.trader(Attempts::Retrying).await {
+            Ok(trader) => {
+                let details = EventDetails::baro(&trader);
+                match self
+                    .sync_event(guild, events.baro.as_ref(), details, now)
+                    .await
+                {
+                    Ok(Some(event)) => events.baro = Some(event),
+                    Ok(None) => {}
+                    Err(e) => {
+                        warning!(context = "syncing baro event", "{e}");
+                        failed = true;
+                    }
+                }
+            }
+            // The failure has already been logged.
+            Err(_) => failed = true,
+        }
+
+        if self.weekly_event {
+            let details = EventDetails::weekly_reset(now);
+            match self
+                .sync_event(guild, events.weekly.as_ref(), details, now)
+                .await
+            {
+                Ok(Some(event)) => events.weekly = Some(event),
+                Ok(None) => {}
+                Err(e) => {
+                    warning!(context = "syncing weekly event", "{e}");
+                    failed = true;
+                }
+            }
+        } else if let Some(event) = events.weekly.take() {
+            // The weekly event was turned off since it was created.
+            let connection = self.connection().await;
+            if let Err(e) = guild.delete_scheduled_event(&connection, event.id).await {
+                warning!(context = "deleting weekly event", "{e}");
+            }
+        }
+
+        self.dumped("scheduled events", events.dump()).await;
+
+        if failed {
+            bail!("some scheduled events could not be synced");
+        }
 
-        self.say_multiple(&messages).await;
+        Ok(())
+    }
+
+    /// Bring a single guild scheduled event in line with the given details.
+    ///
+    /// Returns the event if it was created or changed.
+    async fn sync_event(
+        &self,
+        guild: GuildId,
+        existing: Option<&SyncedEvent>,
+        details: EventDetails,
+        now: DateTime<Utc>,
+    ) -> Result<Option<SyncedEvent>> {
+        let id = match (details.sync_action(existing, now), existing) {
+            (SyncAction::Create, _) => {
+                let connection = self.connection().await;
+                let event = guild
+                    .create_scheduled_event(&connection, details.create())
+                    .await?;
+                info!("created scheduled event \"{}\"", details.name);
+
+                event.id
+            }
+            (SyncAction::Update, Some(existing)) => {
+                let connection = self.connection().await;
+                guild
+                    .edit_scheduled_event(&connection, existing.id, details.edit(now))
+                    .await?;
+                info!("updated scheduled event \"{}\"", details.name);
+
+                existing.id
+            }
+            _ => return Ok(None),
+        };
+
+        Ok(Some(SyncedEvent { id, details }))
+    }
+
+    /// Apply a change to the last visit from Baro Ki'Teer, if there is one, and persist it.
+    async fn update_baro_visit(&self, update: impl FnOnce(&mut BaroVisit)) {
+        let mut visit = self.baro_visit.lock().await;
+        let Some(visit) = visit.as_mut() else {
+            return;
+        };
+
+        update(visit);
+        self.dumped("baro visit", visit.dump()).await;
+    }
+
+    /// Fetches this week's Archon Hunt, alerting the operators if its Archon is not known.
+    pub async fn archon_hunt(&self, attempts: Attempts) -> Result<ArchonHunt, FetchError> {
+        let hunt = self
+            .fetch::<queryable::ArchonHunt>(attempts, "fetching archon")
+            .await?;
+
+        match Archon::from_boss(&hunt.boss) {
+            Some(_) => self.problem_cleared(Problem::UnknownArchon).await,
+            None => {
+                warning!(
+                    context = "fetching archon",
+                    "unknown archon boss: {}",
+                    hunt.boss
+                );
+                self.problem_happened(Problem::UnknownArchon, &hunt.boss)
+                    .await;
+            }
+        }
+
+        Ok(hunt)
+    }
+
+    /// Create a message describing this week's Archon Hunt and the given number of weeks after it,
+    /// from the snapshot of the Archon Hunt.
+    pub async fn archon_messages(&self, weeks_ahead: u32) -> Result<String, FetchError> {
+        let snapshot = self
+            .latest::<queryable::ArchonHunt>("fetching archon")
+            .await?;
+
+        let message = archon_message(&snapshot.value, weeks_ahead);
+        Ok(with_staleness_note(message, &snapshot))
+    }
+
+    /// Create an iCalendar of the upcoming weekly resets, rotations and Baro Ki'Teer's visits,
+    /// from the snapshots of the worldstate. Whatever has never been fetched is left out.
+    pub async fn calendar(&self) -> String {
+        let (trader, archon_hunt) = tokio::join!(
+            self.latest_trader(),
+            self.latest::<queryable::ArchonHunt>("fetching archon")
+        );
+
+        calendar(
+            Utc::now(),
+            trader.ok().map(|snapshot| snapshot.value).as_ref(),
+            archon_hunt.ok().map(|snapshot| snapshot.value).as_ref(),
+        )
+    }
+
+    /// Fetches this week's Archon Hunt and collects it with The Circuit's offerings.
+    pub async fn weekly_info(&self, attempts: Attempts) -> Result<WeeklyInfo, FetchError> {
+        let archon_boss = self.archon_hunt(attempts).await?.boss;
+
+        Ok(WeeklyInfo::new(&archon_boss))
+    }
+
+    /// Returns this week's rotations, from the snapshot of the Archon Hunt.
+    pub async fn latest_weekly_info(&self) -> Result<Snapshot<WeeklyInfo>, FetchError> {
+        let snapshot = self
+            .latest::<queryable::ArchonHunt>("fetching archon")
+            .await?;
+
+        Ok(snapshot.map(|hunt| WeeklyInfo::new(&hunt.boss)))
     }
 
     /// Create the weekly reset message.
-    pub async fn weekly_messages(&self) -> Option<String> {
-        let archon_boss = self
-            .worldstate
-            .fetch::<queryable::ArchonHunt>()
+    pub async fn weekly_messages(&self) -> Result<String, FetchError> {
+        let snapshot = self.latest_weekly_info().await?;
+
+        Ok(with_staleness_note(snapshot.value.as_message(), &snapshot))
+    }
+
+    /// Send a message summarising the weekly reset to the news channel.
+    pub async fn notify_weekly(&self) -> Result<()> {
+        let info = self.weekly_info(Attempts::Retrying).await?;
+
+        let circuit = info
+            .normal_circuit
+            .iter()
+            .chain(&info.sp_circuit)
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        let shards = [info.archon_shard.as_str()];
+
+        let mut mentions = vec![];
+        let mut direct_messages = vec![];
+        for (wishlist, offered) in [
+            (Wishlist::Circuit, &circuit[..]),
+            (Wishlist::ArchonShard, &shards[..]),
+        ] {
+            let subscribers = self.wishlist(wishlist).lock().await;
+
+            for (user, items, direct_message) in subscribers.matching(offered) {
+                let items = items.join(", ");
+                match direct_message {
+                    true => direct_messages.push((user, wishlist.message(&items))),
+                    false => mentions.push(format!("{}: {items}", user.mention())),
+                }
+            }
+        }
+
+        // Mention the subscribers after the post, in as many messages as Discord's limit needs.
+        let mut messages = vec![info.as_message()];
+        messages.extend(join_lines(&mentions, MESSAGE_LIMIT));
+
+        self.enqueue(&messages, None).await?;
+        self.publish(EventKind::Weekly(info));
+        self.direct_message(direct_messages).await;
+
+        self.flush_outbox().await
+    }
+
+    /// Subscribe a user to an item on a wishlist, to be told on the weekly reset when it is
+    /// offered.
+    ///
+    /// Returns `false` if nothing changed.
+    pub async fn subscribe_item(
+        &self,
+        wishlist: Wishlist,
+        user: UserId,
+        item: &str,
+        direct_message: bool,
+    ) -> bool {
+        let mut subscribers = self.wishlist(wishlist).lock().await;
+        let changed = subscribers.subscribe(user, item, direct_message);
+
+        if changed {
+            self.dumped("item subscribers", subscribers.dump()).await;
+        }
+
+        changed
+    }
+
+    /// Unsubscribe a user from an item on a wishlist.
+    ///
+    /// Returns `false` if they were not subscribed to it.
+    pub async fn unsubscribe_item(&self, wishlist: Wishlist, user: UserId, item: &str) -> bool {
+        let mut subscribers = self.wishlist(wishlist).lock().await;
+        let changed = subscribers.unsubscribe(user, item);
+
+        if changed {
+            self.dumped("item subscribers", subscribers.dump()).await;
+        }
+
+        changed
+    }
+
+    /// The items on a wishlist a user is subscribed to.
+    pub async fn item_subscription(
+        &self,
+        wishlist: Wishlist,
+        user: UserId,
+    ) -> Option<ItemSubscription> {
+        self.wishlist(wishlist)
+            .lock()
             .await
-            .inspect_err(|e| warning!(context = "fetching archon", "{e}"))
-            .ok()?
-            .boss;
+            .subscription(user)
+            .cloned()
+    }
 
-        let info = WeeklyInfo::new(&archon_boss);
+    fn wishlist(&self, wishlist: Wishlist) -> &Mutex<ItemSubscribers> {
+        match wishlist {
+            Wishlist::Circuit => &self.circuit_subscribers,
+            Wishlist::ArchonShard => &self.shard_subscribers,
+        }
+    }
+
+    /// The current state of the open worlds, Baro Ki'Teer and the weekly rotations. The dashboard,
+    /// the presence and the timer channel are all updated every minute, so they share what was
+    /// read within the last minute rather than each reading it again.
+    pub async fn dashboard_info(&self) -> DashboardInfo {
+        // Hold the lock while reading, so that concurrent updates wait for one read.
+        let mut cached = self.dashboard_info.lock().await;
+
+        let now = Utc::now();
+        if let Some(snapshot) = cached.as_ref().filter(|snapshot| snapshot.is_fresh(now)) {
+            return snapshot.value.clone();
+        }
+
+        let info = self.read_dashboard_info().await;
+        *cached = Some(Snapshot::new(info.clone(), now));
 
-        Some(info.as_message())
+        info
     }
 
-    /// Send a message summarising the weekly reset to the news channel.
-    pub async fn notify_weekly(&self) {
-        let message = match self.weekly_messages().await {
-            Some(m) => m,
-            None => return,
+    /// Reads the current state of the open worlds, Baro Ki'Teer and the weekly rotations from
+    /// their snapshots, which are fetched again at most once a minute. Whatever has never been
+    /// fetched is left out, as are open world cycles that have ended since they were fetched.
+    async fn read_dashboard_info(&self) -> DashboardInfo {
+        let (cetus, orb_vallis, cambion_drift, trader, weekly) = tokio::join!(
+            self.latest::<queryable::Cetus>("fetching cetus"),
+            self.latest::<queryable::OrbVallis>("fetching orb vallis"),
+            self.latest::<queryable::CambionDrift>("fetching cambion drift"),
+            self.latest_trader(),
+            self.latest_weekly_info(),
+        );
+
+        let now = Utc::now();
+        let current = |info: CycleInfo| (info.expiry > now).then_some(info);
+
+        DashboardInfo {
+            cetus: cetus
+                .ok()
+                .map(|cetus| CycleInfo::new(cetus.value.state, cetus.value.expiry()))
+                .and_then(current),
+            orb_vallis: orb_vallis
+                .ok()
+                .map(|vallis| CycleInfo::new(vallis.value.state, vallis.value.expiry()))
+                .and_then(current),
+            cambion_drift: cambion_drift
+                .ok()
+                .map(|cambion| CycleInfo::new(cambion.value.state, cambion.value.expiry()))
+                .and_then(current),
+            baro: trader.ok().map(|trader| BaroInfo::from(&trader.value)),
+            weekly: weekly.ok().map(|weekly| weekly.value),
+        }
+    }
+
+    /// Post a dashboard of the current timers in the given channel, replacing the previous one.
+    pub async fn create_dashboard(&self, channel_id: ChannelId) -> Result<()> {
+        let content = self.dashboard_info().await.as_message(Utc::now());
+        let connection = self.connection().await;
+        let message = channel_id.say(&connection, content).await?;
+
+        let mut dashboard = self.dashboard.lock().await;
+        if let Some(old) = dashboard.replace(Dashboard::new(channel_id, message.id)) {
+            // Only one dashboard is kept up to date, so the old one would go stale.
+            if let Err(e) = old
+                .channel_id()
+                .delete_message(&connection, old.message_id())
+                .await
+            {
+                warning!(context = "deleting old dashboard", "{e}");
+            }
+        }
+
+        self.dumped("dashboard", Dashboard::dump(*dashboard)).await;
+
+        Ok(())
+    }
+
+    /// Edit the dashboard, if there is one, to show the current timers.
+    pub async fn update_dashboard(&self) -> Result<()> {
+        let Some(dashboard) = *self.dashboard.lock().await else {
+            return Ok(());
+        };
+        // A dashboard posted before switching to webhook-only mode can't be edited.
+        let Some(connection) = self.connection.lock().await.clone() else {
+            return Ok(());
         };
 
-        self.say_multiple(&[message]).await;
+        let content = self.dashboard_info().await.as_message(Utc::now());
+        let result = dashboard
+            .channel_id()
+            .edit_message(
+                &connection,
+                dashboard.message_id(),
+                EditMessage::new().content(content),
+            )
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            // Stop updating a dashboard that was deleted, unless it was replaced in the meantime.
+            Err(serenity::Error::Http(e)) if e.status_code() == Some(StatusCode::NOT_FOUND) => {
+                info!("the dashboard was deleted, no longer updating it");
+
+                let mut current = self.dashboard.lock().await;
+                if *current == Some(dashboard) {
+                    *current = None;
+                    self.dumped("dashboard", Dashboard::dump(None)).await;
+                }
+
+                Ok(())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Set the bot's presence to one of the current timers, rotating through them every minute.
+    pub async fn update_presence(&self) -> Result<()> {
+        let Some(shard) = self.shard.lock().await.clone() else {
+            return Ok(());
+        };
+
+        let now = Utc::now();
+        let statuses = self.dashboard_info().await.statuses(now);
+        let status = &statuses[now.minute() as usize % statuses.len()];
+
+        shard.set_activity(Some(ActivityData::custom(status)));
+
+        Ok(())
+    }
+
+    /// Show the current timers in the timer channel, if there is one. Voice channels are renamed
+    /// to Baro Ki'Teer's countdown, and other channels get every timer in their topic.
+    ///
+    /// Discord only allows a channel to be edited twice every ten minutes, so this should be
+    /// called no more often than that.
+    pub async fn update_timer_channel(&self) -> Result<()> {
+        let Some(channel_id) = self.timer_channel else {
+            return Ok(());
+        };
+
+        let statuses = self.dashboard_info().await.statuses(Utc::now());
+        let connection = self.connection().await;
+        let channel = channel_id
+            .to_channel(&connection)
+            .await?
+            .guild()
+            .ok_or_else(|| anyhow!("the timer channel must be in a server"))?;
+
+        let (text, builder) = match channel.kind {
+            ChannelType::Voice | ChannelType::Stage => {
+                let text = statuses[0].clone();
+                (text.clone(), EditChannel::new().name(text))
+            }
+            _ => {
+                let text = statuses.join(" | ");
+                (text.clone(), EditChannel::new().topic(text))
+            }
+        };
+
+        let mut timer_text = self.timer_text.lock().await;
+        if timer_text.as_ref() == Some(&text) {
+            return Ok(());
+        }
+
+        channel_id.edit(&connection, builder).await?;
+        *timer_text = Some(text);
+
+        Ok(())
+    }
+
+    /// Publish an event to the event feed, if there is one.
+    fn publish(&self, kind: EventKind) {
+        if let Some(events) = &self.events {
+            events.publish(kind);
+        }
+    }
+
+    /// Wait for events still being published to the event feed, if there is one.
+    pub async fn finish_events(&self) {
+        if let Some(events) = &self.events {
+            events.finish().await;
+        }
+    }
+
+    /// Record that a problem happened, alerting the operators if it keeps happening.
+    async fn problem_happened(&self, problem: Problem, error: &impl Display) {
+        let alert = self
+            .alerts
+            .lock()
+            .await
+            .happened(problem, &error.to_string(), Utc::now());
+
+        if let Some(alert) = alert {
+            self.alert(&alert).await;
+        }
+    }
+
+    /// Record that a problem did not happen, telling the operators if it has now cleared.
+    async fn problem_cleared(&self, problem: Problem) {
+        let notice = self.alerts.lock().await.cleared(&problem);
+
+        if let Some(notice) = notice {
+            self.alert(&notice).await;
+        }
+    }
+
+    /// Log the result of saving a cache to disk, alerting the operators if saving keeps failing.
+    async fn dumped(&self, cache: &str, result: Result<()>) {
+        let problem = Problem::Dump(cache.to_string());
+        match result {
+            Ok(()) => self.problem_cleared(problem).await,
+            Err(e) => {
+                warning!(context = format!("dumping {cache}"), "{e}");
+                self.problem_happened(problem, &e).await;
+            }
+        }
+    }
+
+    /// Send an alert to the operators, if somewhere to send alerts was given. Alerts bypass the
+    /// outbox, as the outbox may well be what is failing.
+    async fn alert(&self, content: &str) {
+        let Some(destination) = self.alert_destination else {
+            return;
+        };
+        let Some(connection) = self.connection.lock().await.clone() else {
+            return;
+        };
+
+        let sent = match destination {
+            AlertDestination::Channel(channel_id) => {
+                channel_id.say(&connection, content).await.map(|_| ())
+            }
+            AlertDestination::User(user_id) => user_id
+                .direct_message(&connection, CreateMessage::new().content(content))
+                .await
+                .map(|_| ()),
+        };
+        if let Err(e) = sent {
+            warning!(context = "sending alert", "{e}");
+        }
     }
 
     /// Get the cached connection.
@@ -167,14 +1253,254 @@ impl Handler {
         }
     }
 
-    /// Write multiple messages to the news channel.
-    async fn say_multiple(&self, contents: &[String]) {
-        let connection = self.connection().await;
-        for msg in contents.iter() {
-            if let Err(e) = self.channel_id.say(&connection, msg).await {
-                warning!(context = "sending message", "{e}");
+    /// Get each of the targets that notifications are delivered to.
+    fn targets(&self) -> Result<Vec<Target>> {
+        self.destination
+            .as_ref()
+            .map(Destination::targets)
+            .ok_or_else(|| anyhow!("no news channel or webhook configured"))
+    }
+
+    /// Queue messages about the given subject for delivery to every target.
+    async fn enqueue(&self, contents: &[String], subject: Option<Subject>) -> Result<()> {
+        let targets = self.targets()?;
+
+        let mut outbox = self.outbox.lock().await;
+        for target in targets {
+            for msg in contents.iter() {
+                outbox.push(&target.key(), msg.clone(), subject.clone());
             }
         }
+
+        self.dumped("outbox", outbox.dump()).await;
+
+        Ok(())
+    }
+
+    /// Attempt to deliver every message in the outbox that is due, marking news items as seen by
+    /// the target they were delivered to, and remembering posts of Baro Ki'Teer's inventory.
+    /// Failed messages are retried later with exponential backoff.
+    pub async fn flush_outbox(&self) -> Result<()> {
+        let targets: HashMap<String, Target> = self
+            .destination
+            .iter()
+            .flat_map(Destination::targets)
+            .map(|target| (target.key(), target))
+            .collect();
+
+        let _flushing = self.flushing.lock().await;
+
+        let now = Utc::now();
+        let due = self.outbox.lock().await.due(now);
+        if due.is_empty() {
+            return Ok(());
+        }
+
+        // Send without holding the outbox or the caches, which other tasks need in the meantime.
+        // The messages stay in the outbox until the results are recorded below.
+        let mut failed_targets = HashSet::new();
+        let mut results = vec![];
+        for message in due {
+            // Keep messages to the same target in order.
+            if failed_targets.contains(&message.target) {
+                continue;
+            }
+
+            let result = match targets.get(&message.target) {
+                Some(target) => self.send(target, &message.content).await,
+                None => Err(anyhow!("target is no longer configured")),
+            };
+            metrics::message_delivered(result.is_ok());
+
+            if result.is_err() {
+                failed_targets.insert(message.target.clone());
+            }
+            results.push((message, result));
+        }
+
+        // Get a handle for the outbox and the caches, in that order.
+        let mut outbox = self.outbox.lock().await;
+        let mut caches = self.news_caches.lock().await;
+
+        let mut problems = vec![];
+        let mut finished_news = vec![];
+        let mut baro_posts = vec![];
+        for (message, result) in results {
+            let problem = Problem::Send(message.target.clone());
+            match result {
+                Ok(message_id) => {
+                    problems.push((problem, None));
+                    match outbox.mark_delivered(message.id).and_then(|m| m.subject) {
+                        Some(Subject::News(news_item)) => {
+                            let post = PostedNews::new(news_item, Some(message_id));
+                            finished_news.push((message.target.clone(), post));
+                        }
+                        Some(Subject::BaroVisit(expiry)) => {
+                            let post = PostedMessage {
+                                target: message.target.clone(),
+                                id: message_id,
+                            };
+                            baro_posts.push((expiry, post));
+                        }
+                        None => {}
+                    }
+                }
+                Err(e) => {
+                    warning!(context = "sending message", "{e}");
+
+                    // News given up on stays unseen, so it is queued again with the next news.
+                    match outbox.mark_failed(message.id, now) {
+                        Some(Failure::Retrying(at)) => {
+                            info!("retrying message to {} at {at}", message.target)
+                        }
+                        Some(Failure::GaveUp(message)) => error!(
+                            context = "sending message",
+                            "giving up on message to {}: {}", message.target, message.content
+                        ),
+                        None => {}
+                    }
+                    problems.push((problem, Some(e)));
+                }
+            }
+        }
+
+        let mut dumps = vec![("outbox", outbox.dump())];
+        metrics::cache_size("outbox", outbox.len());
+
+        // Mark the news as seen by the targets it was delivered to.
+        let mut changed = HashSet::new();
+        for (key, post) in finished_news {
+            if seen_cache(&mut caches, &key).insert(post) {
+                changed.insert(key);
+            }
+        }
+        for key in changed {
+            let cache = seen_cache(&mut caches, &key);
+            metrics::cache_size(&format!("news_{key}"), cache.len());
+
+            dumps.push(("news cache", cache.dump()));
+        }
+        drop(caches);
+        drop(outbox);
+
+        // Alerts are sent right away, so only once the outbox and the caches are released.
+        for (problem, error) in problems {
+            match error {
+                None => self.problem_cleared(problem).await,
+                Some(e) => self.problem_happened(problem, &e).await,
+            }
+        }
+        for (cache, result) in dumps {
+            self.dumped(cache, result).await;
+        }
+
+        // Remember the posts of Baro Ki'Teer's inventory, if they are for his current visit.
+        if !baro_posts.is_empty() {
+            self.update_baro_visit(|visit| {
+                visit.messages.extend(
+                    baro_posts
+                        .into_iter()
+                        .filter(|(expiry, _)| *expiry == visit.expiry)
+                        .map(|(_, post)| post),
+                );
+            })
+            .await;
+        }
+
+        if !failed_targets.is_empty() {
+            bail!("some messages could not be delivered");
+        }
+
+        Ok(())
+    }
+
+    /// Send a single message to a target, returning the ID of the sent message.
+    async fn send(&self, target: &Target, content: &str) -> Result<MessageId> {
+        match target {
+            Target::Channel(channel_id) => {
+                let connection = self.connection().await;
+                Ok(channel_id.say(&connection, content).await?.id)
+            }
+            Target::Webhook(webhook) => webhook.say(content).await,
+        }
+    }
+
+    /// Replace the content of a message previously sent to a target.
+    async fn edit(&self, target: &Target, message_id: MessageId, content: &str) -> Result<()> {
+        match target {
+            Target::Channel(channel_id) => {
+                let connection = self.connection().await;
+                let builder = EditMessage::new().content(content);
+                channel_id
+                    .edit_message(&connection, message_id, builder)
+                    .await?;
+                Ok(())
+            }
+            Target::Webhook(webhook) => webhook.edit(message_id, content).await,
+        }
+    }
+}
+
+/// Returns `true` if a message could not be edited because it was deleted, or the bot may no
+/// longer edit it, so that trying again would not help.
+fn is_gone(e: &anyhow::Error) -> bool {
+    let status = match e.downcast_ref::<serenity::Error>() {
+        Some(serenity::Error::Http(e)) => e.status_code().map(|status| status.as_u16()),
+        _ => e
+            .downcast_ref::<reqwest::Error>()
+            .and_then(reqwest::Error::status)
+            .map(|status| status.as_u16()),
+    };
+
+    matches!(status, Some(403 | 404))
+}
+
+/// Get the cache of news seen by the target with the given key, loading it if necessary.
+fn seen_cache<'a>(
+    caches: &'a mut HashMap<String, SeenCache<PostedNews, 20>>,
+    key: &str,
+) -> &'a mut SeenCache<PostedNews, 20> {
+    caches
+        .entry(key.to_string())
+        .or_insert_with(|| load_posts(key))
+}
+
+/// Load the posts seen by the target with the given key. If the target has no posts yet, the news
+/// seen by the single channel from before there were targets is carried over, so that it is not
+/// posted again.
+fn load_posts(key: &str) -> SeenCache<PostedNews, 20> {
+    let mut posts = SeenCache::new(&format!("POSTS_{key}"));
+    if !posts.is_empty() {
+        return posts;
+    }
+
+    if let Some(news) = SeenCache::<News, 20>::existing("NEWS").filter(|news| !news.is_empty()) {
+        for news_item in news.iter() {
+            posts.insert(PostedNews::new(news_item.clone(), None));
+        }
+        info!("carried over {} seen news items for {key}", posts.len());
+    }
+
+    posts
+}
+
+/// Convert the news from the worldstate, ignoring blacklisted items.
+fn white_listed_news(news: Vec<queryable::News>) -> Vec<News> {
+    news.into_iter()
+        .filter_map(|news_item| {
+            let mapped = News::from(news_item);
+
+            // Ignore news that is alway active, e.g., "Join the Warframe Discord!"
+            white_listed(&mapped).then_some(mapped)
+        })
+        .collect()
+}
+
+/// Add a note to a message saying how old the snapshot it was made from is, if it is old.
+fn with_staleness_note<T>(message: String, snapshot: &Snapshot<T>) -> String {
+    match snapshot.staleness_note(Utc::now()) {
+        Some(note) => format!("{message}\n{note}"),
+        None => message,
     }
 }
 
@@ -1,19 +1,23 @@
+use chrono::{DateTime, TimeDelta, Utc};
 use itertools::Itertools;
 use poise::serenity_prelude::MessageBuilder;
+use serde::Serialize;
 use warframe::worldstate::items::Item;
 use warframe::worldstate::queryable::VoidTrader;
 use warframe::worldstate::{TimedEvent, VoidTraderInventoryItem};
 
+use crate::archon::Archon;
 use crate::circuit::{circuit, sp_circuit};
+use crate::{DateStyle, fmt_time};
 
-pub async fn calculate_baro_string(trader: &VoidTrader) -> Vec<String> {
+pub async fn calculate_baro_string(trader: &VoidTrader, style: DateStyle) -> Vec<String> {
     let max_tables = 2;
     if trader.active() {
         // Generate overview message
         let mut time_info = format!(
             "Baro Ki'Teer is at {} until {}.",
             trader.location,
-            trader.expiry().format("%a, %b %d at %-I:%M %p")
+            fmt_time(&trader.expiry(), style)
         );
 
         // Calculate the tables...
@@ -45,7 +49,7 @@ pub async fn calculate_baro_string(trader: &VoidTrader) -> Vec<String> {
         let msg = format!(
             "Baro Ki'Teer will be at {} on {}.",
             trader.location,
-            trader.activation().format("%a, %b %d"),
+            fmt_time(&trader.activation(), style),
         );
 
         vec![msg]
@@ -93,20 +97,56 @@ async fn format_baro_inventory(items: &[VoidTraderInventoryItem]) -> Vec<String>
         .collect::<Vec<_>>()
 }
 
+/// Serialisable summary of Baro Ki'Teer's current or next visit.
+#[derive(Debug, Clone, Serialize)]
+pub struct BaroInfo {
+    pub active: bool,
+    pub location: String,
+    pub activation: DateTime<Utc>,
+    pub expiry: DateTime<Utc>,
+    pub inventory: Vec<BaroItem>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BaroItem {
+    pub item: String,
+    pub ducats: i32,
+    pub credits: i32,
+}
+
+impl From<&VoidTrader> for BaroInfo {
+    fn from(trader: &VoidTrader) -> Self {
+        Self {
+            active: trader.active(),
+            location: trader.location.clone(),
+            activation: trader.activation(),
+            expiry: trader.expiry(),
+            inventory: trader
+                .inventory
+                .iter()
+                .map(|item| BaroItem {
+                    item: item.item.inner().to_string(),
+                    ducats: item.ducats,
+                    credits: item.credits,
+                })
+                .collect(),
+        }
+    }
+}
+
 /// Generate the name of the archon shard from the boss's name.
 pub fn format_archon(boss: &str) -> String {
-    match boss.to_lowercase() {
-        s if s.contains("amar") => "Crimson Archon Shard".to_string(),
-        s if s.contains("nira") => "Amber Archon Shard".to_string(),
-        s if s.contains("boreal") => "Azure Archon Shard".to_string(),
-        s => format!("Unknown Archon Boss: {s}"),
+    match Archon::from_boss(boss) {
+        Some(archon) => archon.shard().to_string(),
+        None => format!("Unknown Archon Boss: {boss}"),
     }
 }
 
+#[derive(Debug, Clone, Serialize)]
 pub struct WeeklyInfo {
     pub archon_shard: String,
-    pub normal_circuit: [&'static str; 3],
-    pub sp_circuit: [&'static str; 5],
+    pub normal_circuit: Vec<String>,
+    pub sp_circuit: Vec<String>,
 }
 
 impl WeeklyInfo {
@@ -127,16 +167,18 @@ impl WeeklyInfo {
         table.column(1).set_header("The Circit (Steel Path)").set_align(Center);
         table.column(2).set_header("Archon Hunt").set_align(Center);
 
-        let circ = self.normal_circuit;
-        let spc = self.sp_circuit;
+        let circ = &self.normal_circuit;
+        let spc = &self.sp_circuit;
+        fn cell(items: &[String], row: usize) -> &str {
+            items.get(row).map_or("", String::as_str)
+        }
 
-        let data = vec![
-            vec![circ[0], spc[0], &self.archon_shard],
-            vec![circ[1], spc[1]],
-            vec![circ[2], spc[2]],
-            vec!["", spc[3]],
-            vec!["", spc[4]],
-        ];
+        let data = (0..circ.len().max(spc.len()))
+            .map(|row| match row {
+                0 => vec![cell(circ, row), cell(spc, row), &self.archon_shard],
+                _ => vec![cell(circ, row), cell(spc, row)],
+            })
+            .collect::<Vec<_>>();
 
         let table = table.format(data);
 
@@ -153,6 +195,150 @@ impl WeeklyInfo {
     }
 }
 
+/// Serialisable summary of this week's Circuit.
+#[derive(Debug, Clone, Serialize)]
+pub struct CircuitInfo {
+    pub normal: Vec<String>,
+    pub steel_path: Vec<String>,
+}
+
+impl CircuitInfo {
+    /// This week's offerings.
+    pub fn current() -> Self {
+        Self {
+            normal: circuit(),
+            steel_path: sp_circuit(),
+        }
+    }
+}
+
+/// The state of one of the open worlds' day/night-style cycles.
+#[derive(Debug, Clone, Serialize)]
+pub struct CycleInfo {
+    pub state: String,
+    pub expiry: DateTime<Utc>,
+}
+
+impl CycleInfo {
+    pub fn new(state: impl ToString, expiry: DateTime<Utc>) -> Self {
+        Self {
+            state: state.to_string(),
+            expiry,
+        }
+    }
+}
+
+/// A snapshot of the worldstate's timers, as shown on the dashboard. Anything that could not be
+/// fetched is `None`.
+#[derive(Debug, Clone, Serialize)]
+pub struct DashboardInfo {
+    pub cetus: Option<CycleInfo>,
+    pub orb_vallis: Option<CycleInfo>,
+    pub cambion_drift: Option<CycleInfo>,
+    pub baro: Option<BaroInfo>,
+    pub weekly: Option<WeeklyInfo>,
+}
+
+impl DashboardInfo {
+    /// Convert the dashboard into a Discord message, with timers counting from the given time.
+    pub fn as_message(&self, now: DateTime<Utc>) -> String {
+        let unknown = || "unknown".to_string();
+        let cycle = |info: &Option<CycleInfo>| {
+            info.as_ref().map_or_else(unknown, |info| {
+                let left = format_duration(info.expiry - now);
+                format!("{} ({left} left)", info.state)
+            })
+        };
+
+        let baro = self
+            .baro
+            .as_ref()
+            .map_or_else(unknown, |baro| match baro.active {
+                true => format!(
+                    "at {}, leaving in {}",
+                    baro.location,
+                    format_duration(baro.expiry - now)
+                ),
+                false => format!(
+                    "arriving at {} in {}",
+                    baro.location,
+                    format_duration(baro.activation - now)
+                ),
+            });
+
+        let (archon, circuit, sp_circuit) = match &self.weekly {
+            Some(weekly) => (
+                weekly.archon_shard.clone(),
+                weekly.normal_circuit.join(", "),
+                weekly.sp_circuit.join(", "),
+            ),
+            None => (unknown(), unknown(), unknown()),
+        };
+
+        [
+            "**Worldstate**".to_string(),
+            format!("Cetus: {}", cycle(&self.cetus)),
+            format!("Orb Vallis: {}", cycle(&self.orb_vallis)),
+            format!("Cambion Drift: {}", cycle(&self.cambion_drift)),
+            format!("Baro Ki'Teer: {baro}"),
+            format!("Archon Hunt: {archon}"),
+            format!("The Circuit: {circuit}"),
+            format!("The Circuit (Steel Path): {sp_circuit}"),
+            format!(
+                "Daily reset in {}",
+                format_duration(crate::next_daily_reset(now) - now)
+            ),
+            format!(
+                "Weekly reset in {}",
+                format_duration(crate::next_weekly_reset(now) - now)
+            ),
+        ]
+        .join("\n")
+    }
+
+    /// Short statuses for the bot's presence, e.g., "Baro in 3d 4h" or "Cetus: Night 12m", with
+    /// timers counting from the given time. Baro Ki'Teer's countdown comes first, if known.
+    pub fn statuses(&self, now: DateTime<Utc>) -> Vec<String> {
+        let mut statuses = vec![];
+
+        if let Some(baro) = &self.baro {
+            statuses.push(match baro.active {
+                true => format!("Baro leaves in {}", format_duration(baro.expiry - now)),
+                false => format!("Baro in {}", format_duration(baro.activation - now)),
+            });
+        }
+
+        let cycles = [
+            ("Cetus", &self.cetus),
+            ("Vallis", &self.orb_vallis),
+            ("Cambion", &self.cambion_drift),
+        ];
+        for (name, cycle) in cycles {
+            if let Some(cycle) = cycle {
+                let left = format_duration(cycle.expiry - now);
+                statuses.push(format!("{name}: {} {left}", cycle.state));
+            }
+        }
+
+        let reset = format_duration(crate::next_daily_reset(now) - now);
+        statuses.push(format!("Reset in {reset}"));
+
+        statuses
+    }
+}
+
+/// Formats a duration to the minute, e.g., "3d 4h", "4h 12m", or "12m".
+pub fn format_duration(duration: TimeDelta) -> String {
+    let minutes = duration.num_minutes().max(0);
+    let (days, hours, minutes) = (minutes / (60 * 24), minutes / 60 % 24, minutes % 60);
+
+    match (days, hours) {
+        (0, 0) => format!("{minutes}m"),
+        (0, _) => format!("{hours}h {minutes}m"),
+        _ => format!("{days}d {hours}h"),
+    }
+}
+
 /// Formats large numbers using k (thousands) and M (millions) where appropriate.
 ///
 /// Primarily used to display credits.
@@ -202,3 +388,40 @@ fn variant_group(e: &Item) -> usize {
         Fish(_) | Node(_) | Misc(_) => 3,
     }
 }
+
+#[cfg(test)]
+mod item_display_test {
+    use super::*;
+
+    #[test]
+    fn format_duration_units() {
+        assert_eq!(format_duration(TimeDelta::seconds(59)), "0m");
+        assert_eq!(format_duration(TimeDelta::minutes(12)), "12m");
+        assert_eq!(format_duration(TimeDelta::minutes(4 * 60 + 12)), "4h 12m");
+        assert_eq!(format_duration(TimeDelta::hours(3 * 24 + 4)), "3d 4h");
+        assert_eq!(format_duration(TimeDelta::minutes(-5)), "0m");
+    }
+
+    #[test]
+    fn statuses() {
+        let now = "2025-07-21T12:00:00Z".parse().unwrap();
+        let info = DashboardInfo {
+            cetus: Some(CycleInfo::new("Night", now + TimeDelta::minutes(12))),
+            orb_vallis: None,
+            cambion_drift: None,
+            baro: Some(BaroInfo {
+                active: false,
+                location: "Strata Relay (Earth)".into(),
+                activation: now + TimeDelta::hours(3 * 24 + 4),
+                expiry: now + TimeDelta::hours(5 * 24 + 4),
+                inventory: vec![],
+            }),
+            weekly: None,
+        };
+
+        assert_eq!(
+            info.statuses(now),
+            ["Baro in 3d 4h", "Cetus: Night 12m", "Reset in 12h 0m"]
+        );
+    }
+}
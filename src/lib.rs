@@ -1,21 +1,142 @@
+pub mod alerts;
+mod archon;
+mod baro;
 mod blacklist;
 mod cache;
-mod circuit;
+mod calendar;
+pub mod circuit;
 pub mod cli;
 pub mod commands;
+mod dashboard;
+mod discord_id;
+pub mod events;
+pub mod fetch;
 pub mod handler;
 mod item_display;
 pub mod logging;
+pub mod metrics;
 mod news_wrapper;
+mod outbox;
 pub mod periodic;
+pub mod print;
+pub mod run_once;
+mod scheduled_events;
+pub mod server;
+mod subscribers;
+#[cfg(test)]
+mod test_util;
+pub mod webhook;
 
 pub use blacklist::BLACKLIST;
 pub use news_wrapper::*;
 
-use anyhow::Result;
+use chrono::{DateTime, Datelike, NaiveTime, TimeDelta, Utc};
+
+/// The most characters Discord accepts in a single message.
+pub const MESSAGE_LIMIT: usize = 2000;
+
+/// How dates and times are written in messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateStyle {
+    /// Discord timestamps, which every client shows in its own timezone.
+    Discord,
+    /// Plain text in UTC, for output outside of Discord.
+    Plain,
+}
 
 /// Format the date style given by the warframe API.
-pub fn fmt_api_date(date: &chrono::DateTime<chrono::Utc>) -> Result<String> {
-    let local = date.with_timezone(&chrono::Local);
-    Ok(format!("{}", local.format("%a, %b %d")))
+pub fn fmt_api_date(date: &DateTime<Utc>, style: DateStyle) -> String {
+    match style {
+        DateStyle::Discord => format!("<t:{}:D>", date.timestamp()),
+        DateStyle::Plain => date.format("%a, %b %d UTC").to_string(),
+    }
+}
+
+/// Format a date and time. On Discord this includes a live countdown.
+pub fn fmt_time(date: &DateTime<Utc>, style: DateStyle) -> String {
+    match style {
+        DateStyle::Discord => format!("<t:{0}:F> (<t:{0}:R>)", date.timestamp()),
+        DateStyle::Plain => date.format("%a, %b %d at %H:%M UTC").to_string(),
+    }
+}
+
+/// Join lines into as few messages as possible, each within the given number of characters.
+pub fn join_lines(lines: &[String], limit: usize) -> Vec<String> {
+    let mut messages: Vec<String> = vec![];
+
+    for line in lines {
+        match messages.last_mut() {
+            Some(message) if message.chars().count() + 1 + line.chars().count() <= limit => {
+                message.push('\n');
+                message.push_str(line);
+            }
+            _ => messages.push(line.clone()),
+        }
+    }
+
+    messages
+}
+
+/// The start of the first day after the given time. Days start at 00:00 UTC.
+pub fn next_daily_reset(now: DateTime<Utc>) -> DateTime<Utc> {
+    let tomorrow = now.date_naive() + TimeDelta::days(1);
+    tomorrow.and_time(NaiveTime::MIN).and_utc()
+}
+
+/// The start of the first week after the given time. Weeks start on Monday at 00:00 UTC.
+pub fn next_weekly_reset(now: DateTime<Utc>) -> DateTime<Utc> {
+    let days_until_monday = 7 - i64::from(now.weekday().num_days_from_monday());
+    let monday = now.date_naive() + TimeDelta::days(days_until_monday);
+
+    monday.and_time(NaiveTime::MIN).and_utc()
+}
+
+#[cfg(test)]
+mod lib_test {
+    use super::*;
+
+    #[test]
+    fn date_styles() {
+        let date = "2025-06-13T14:00:00Z".parse().unwrap();
+
+        assert_eq!(fmt_api_date(&date, DateStyle::Discord), "<t:1749823200:D>");
+        assert_eq!(fmt_api_date(&date, DateStyle::Plain), "Fri, Jun 13 UTC");
+        assert_eq!(
+            fmt_time(&date, DateStyle::Discord),
+            "<t:1749823200:F> (<t:1749823200:R>)"
+        );
+        assert_eq!(
+            fmt_time(&date, DateStyle::Plain),
+            "Fri, Jun 13 at 14:00 UTC"
+        );
+    }
+
+    #[test]
+    fn join_lines_within_limit() {
+        let lines = ["a".repeat(6), "b".repeat(3), "c".repeat(6)].map(String::from);
+
+        assert_eq!(join_lines(&lines, 10), ["aaaaaa\nbbb", "cccccc"]);
+        assert_eq!(join_lines(&lines, 100).len(), 1);
+        assert!(join_lines(&[], 10).is_empty());
+    }
+
+    #[test]
+    fn next_daily_reset_is_midnight() {
+        let evening = "2025-07-20T23:59:00Z".parse().unwrap();
+        let midnight = "2025-07-21T00:00:00Z".parse().unwrap();
+        let next_midnight = "2025-07-22T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+
+        assert_eq!(next_daily_reset(evening), midnight);
+        assert_eq!(next_daily_reset(midnight), next_midnight);
+    }
+
+    #[test]
+    fn next_weekly_reset_is_monday() {
+        let sunday = "2025-07-20T23:59:00Z".parse().unwrap();
+        let monday = "2025-07-21T00:00:00Z".parse().unwrap();
+        let next_monday = "2025-07-28T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+
+        assert_eq!(next_weekly_reset(sunday), monday);
+        assert_eq!(next_weekly_reset(monday), next_monday);
+    }
 }
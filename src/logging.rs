@@ -1,23 +1,86 @@
+use crate::cli::LogFormat;
+
+use std::io::IsTerminal;
+
+use poise::serenity_prelude::{self as serenity, FullEvent, Interaction};
+use tracing::{Instrument, info_span};
+use tracing_subscriber::EnvFilter;
+
+/// The environment variable that sets which logs are shown, e.g., `debug` or
+/// `wf_bot=debug,serenity=info`.
+pub const FILTER_ENV: &str = "WF_LOG";
+
+/// The logs shown when no filter is given: the bot's own, and only problems from its libraries.
+const DEFAULT_FILTER: &str = "warn,wf_bot=info";
+
+/// Log to stderr in the given format, at the levels set by [`FILTER_ENV`]. Text is coloured only
+/// when stderr is a terminal.
+pub fn init(format: LogFormat) {
+    let filter =
+        EnvFilter::try_from_env(FILTER_ENV).unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    let logger = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+
+    match format {
+        LogFormat::Text => logger.with_ansi(std::io::stderr().is_terminal()).init(),
+        LogFormat::Json => logger.json().with_current_span(true).init(),
+    }
+}
+
+/// Wraps a framework so that everything logged while handling a slash command is logged within a
+/// `command` span naming the command and the user who used it.
+pub struct CommandSpans<F>(pub F);
+
+#[serenity::async_trait]
+impl<F: serenity::Framework> serenity::Framework for CommandSpans<F> {
+    async fn init(&mut self, client: &serenity::Client) {
+        self.0.init(client).await
+    }
+
+    async fn dispatch(&self, ctx: serenity::Context, event: FullEvent) {
+        let span = match &event {
+            FullEvent::InteractionCreate {
+                interaction: Interaction::Command(command),
+            } => info_span!(
+                "command",
+                name = %command.data.name,
+                user = %command.user.name,
+                guild = command.guild_id.map(u64::from),
+            ),
+            _ => return self.0.dispatch(ctx, event).await,
+        };
+
+        self.0.dispatch(ctx, event).instrument(span).await
+    }
+}
+
 #[macro_export]
 macro_rules! info {
-    ($($arg:tt)*) => {{
-        use colored::Colorize;
-        eprintln!("{}: {}", "[Info]".green(), format_args!($($arg)*));
-    }};
+    (context = $ctx:expr, $($arg:tt)*) => {
+        ::tracing::info!(context = %$ctx, $($arg)*)
+    };
+    ($($arg:tt)*) => {
+        ::tracing::info!($($arg)*)
+    };
 }
 
 #[macro_export]
 macro_rules! warning {
-    (context = $ctx:expr, $($arg:tt)*) => {{
-        use colored::Colorize;
-        eprintln!("{}: {}", format!("[Warning -- {}]", $ctx).yellow(), format_args!($($arg)*));
-    }};
+    (context = $ctx:expr, $($arg:tt)*) => {
+        ::tracing::warn!(context = %$ctx, $($arg)*)
+    };
+    ($($arg:tt)*) => {
+        ::tracing::warn!($($arg)*)
+    };
 }
 
 #[macro_export]
 macro_rules! error {
-    (context = $ctx:expr, $($arg:tt)*) => {{
-        use colored::Colorize;
-        eprintln!("{}: {}", format!("[Error -- {}]", $ctx).red(), format_args!($($arg)*));
-    }};
+    (context = $ctx:expr, $($arg:tt)*) => {
+        ::tracing::error!(context = %$ctx, $($arg)*)
+    };
+    ($($arg:tt)*) => {
+        ::tracing::error!($($arg)*)
+    };
 }
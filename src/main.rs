@@ -1,26 +1,99 @@
 use std::boxed::Box;
 use std::sync::Arc;
 
+use chrono::TimeDelta;
 use clap::Parser;
-use poise::serenity_prelude as serenity;
-use wf_bot::{cli::Cli, commands::*, handler, periodic, warning};
+use poise::serenity_prelude::{self as serenity, ChannelId};
+use wf_bot::cli::{Cli, Command};
+use wf_bot::handler::Handler;
+use wf_bot::{
+    circuit, commands::*, error, info, logging, metrics, periodic, print, run_once, server, warning,
+};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let _ = dotenv::dotenv();
     let args = Cli::parse();
+    logging::init(args.log_format);
 
-    // Create a new handler and client.
-    let handler = Arc::new(handler::Handler::new(args.channel_id.into()));
+    // Load The Circuit's rotations up front, so that a broken rotation file is noticed at once.
+    circuit::set_source(args.circuit_file.clone())?;
+
+    // Handle one-off commands that do not need a connection to the Discord gateway.
+    match &args.command {
+        Some(Command::Print { subject, format }) => {
+            return print::print(*subject, *format).await;
+        }
+        Some(Command::RunOnce { tasks }) => {
+            let handler = new_handler(&args);
+            if !args.webhook_only() {
+                handler
+                    .init_connection(Arc::new(serenity::Http::new(args.api_token())))
+                    .await;
+            }
+
+            return run_once::run_once(&handler, tasks).await;
+        }
+        None => {}
+    }
+
+    // Create a new handler.
+    let handler = Arc::new(new_handler(&args));
+
+    if let Some(addr) = args.metrics_addr {
+        tokio::spawn(async move {
+            if let Err(e) = server::serve_metrics(addr).await {
+                error!(context = "metrics server", "{e}");
+            }
+        });
+    }
+    if let Some(addr) = args.http_addr {
+        let handler = handler.clone();
+        tokio::spawn(async move {
+            if let Err(e) = server::serve(addr, handler).await {
+                error!(context = "http server", "{e}");
+            }
+        });
+    }
+
+    // Without a connection to Discord, there is nothing to do but run the periodic tasks.
+    if args.webhook_only() {
+        info!("running in webhook-only mode. slash commands are disabled.");
+        periodic::start_tasks(handler.clone()).await;
+        std::future::pending::<()>().await;
+    }
+
+    // Create the client.
     let framework = poise::Framework::builder()
         .options(poise::FrameworkOptions {
-            commands: vec![baro(), news(), weekly(), help()],
+            commands: vec![
+                archon(),
+                archon_reminders(),
+                baro(),
+                baro_reminders(),
+                calendar(),
+                circuit(),
+                dashboard(),
+                news(),
+                weekly(),
+                help(),
+            ],
+            pre_command: |ctx| {
+                Box::pin(async move { metrics::command_invoked(&ctx.command().qualified_name) })
+            },
+            event_handler: |_ctx, event, _framework, handler| {
+                Box::pin(async move {
+                    handler.gateway_event(event).await;
+                    Ok(())
+                })
+            },
             ..Default::default()
         })
         .setup(move |ctx, _ready, framework| {
             let handler = handler.clone();
             Box::pin(async move {
                 handler.init_connection(ctx.http.clone()).await;
+                handler.init_shard(ctx.shard.clone()).await;
                 periodic::start_tasks(handler.clone()).await;
 
                 match args.guild_id {
@@ -49,8 +122,8 @@ async fn main() -> anyhow::Result<()> {
         .build();
 
     let mut client =
-        serenity::Client::builder(&args.api_token, serenity::GatewayIntents::non_privileged())
-            .framework(framework)
+        serenity::Client::builder(args.api_token(), serenity::GatewayIntents::non_privileged())
+            .framework(logging::CommandSpans(framework))
             .await?;
 
     // Respond to user messages.
@@ -58,3 +131,18 @@ async fn main() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// Create a handler that delivers notifications as configured by the command line arguments.
+fn new_handler(args: &Cli) -> Handler {
+    Handler::new(Some(args.destination()))
+        .with_event_feed(args.event_feed())
+        .with_backfill(args.backfill)
+        .with_leaving_reminder(
+            args.baro_leaving_reminder
+                .map(|hours| TimeDelta::hours(hours.into())),
+        )
+        .with_arrival_reminders(args.baro_arrival_reminders.clone())
+        .with_scheduled_events(args.event_guild(), args.weekly_event)
+        .with_timer_channel(args.timer_channel.map(ChannelId::new))
+        .with_alerts(args.alert_destination())
+}
@@ -0,0 +1,156 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use prometheus::{
+    Encoder, GaugeVec, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+
+/// The bot's metrics, registered in their own registry.
+static METRICS: Lazy<Metrics> = Lazy::new(|| Metrics::new().expect("the metrics are valid"));
+
+struct Metrics {
+    registry: Registry,
+    fetch_seconds: HistogramVec,
+    fetch_errors: IntCounterVec,
+    messages: IntCounterVec,
+    commands: IntCounterVec,
+    cache_items: IntGaugeVec,
+    task_last_run: GaugeVec,
+}
+
+impl Metrics {
+    fn new() -> Result<Self> {
+        let registry = Registry::new_custom(Some("wf_bot".to_string()), None)?;
+
+        let fetch_seconds = HistogramVec::new(
+            HistogramOpts::new(
+                "worldstate_fetch_seconds",
+                "How long fetching from the worldstate took.",
+            ),
+            &["queryable"],
+        )?;
+        let fetch_errors = IntCounterVec::new(
+            Opts::new(
+                "worldstate_fetch_errors_total",
+                "Fetches from the worldstate that failed.",
+            ),
+            &["queryable"],
+        )?;
+        let messages = IntCounterVec::new(
+            Opts::new(
+                "messages_total",
+                "Messages delivered, by whether they were sent.",
+            ),
+            &["result"],
+        )?;
+        let commands = IntCounterVec::new(
+            Opts::new("commands_total", "Slash commands invoked."),
+            &["command"],
+        )?;
+        let cache_items = IntGaugeVec::new(
+            Opts::new("cache_items", "Items held in each of the persisted caches."),
+            &["cache"],
+        )?;
+        let task_last_run = GaugeVec::new(
+            Opts::new(
+                "task_last_run_timestamp_seconds",
+                "When each periodic task last ran, as a Unix timestamp.",
+            ),
+            &["task"],
+        )?;
+
+        registry.register(Box::new(fetch_seconds.clone()))?;
+        registry.register(Box::new(fetch_errors.clone()))?;
+        registry.register(Box::new(messages.clone()))?;
+        registry.register(Box::new(commands.clone()))?;
+        registry.register(Box::new(cache_items.clone()))?;
+        registry.register(Box::new(task_last_run.clone()))?;
+
+        Ok(Self {
+            registry,
+            fetch_seconds,
+            fetch_errors,
+            messages,
+            commands,
+            cache_items,
+            task_last_run,
+        })
+    }
+}
+
+/// Record a fetch of the given model from the worldstate.
+pub fn worldstate_fetched(queryable: &str, took: Duration, succeeded: bool) {
+    METRICS
+        .fetch_seconds
+        .with_label_values(&[queryable])
+        .observe(took.as_secs_f64());
+    if !succeeded {
+        METRICS.fetch_errors.with_label_values(&[queryable]).inc();
+    }
+}
+
+/// Record an attempt to deliver a message.
+pub fn message_delivered(sent: bool) {
+    let result = match sent {
+        true => "sent",
+        false => "failed",
+    };
+
+    METRICS.messages.with_label_values(&[result]).inc();
+}
+
+/// Record an invocation of the slash command with the given name.
+pub fn command_invoked(command: &str) {
+    METRICS.commands.with_label_values(&[command]).inc();
+}
+
+/// Record the number of items in the given cache.
+pub fn cache_size(cache: &str, items: usize) {
+    METRICS
+        .cache_items
+        .with_label_values(&[cache])
+        .set(items as i64);
+}
+
+/// Record that the given periodic task has run.
+pub fn task_ran(task: &str) {
+    METRICS
+        .task_last_run
+        .with_label_values(&[task])
+        .set(Utc::now().timestamp() as f64);
+}
+
+/// The metrics in the Prometheus text format.
+pub fn encode() -> Result<String> {
+    let mut buffer = vec![];
+    TextEncoder::new().encode(&METRICS.registry.gather(), &mut buffer)?;
+
+    Ok(String::from_utf8(buffer)?)
+}
+
+#[cfg(test)]
+mod metrics_test {
+    use super::*;
+
+    #[test]
+    fn encode_metrics() {
+        // The metrics are shared by every test, so only labels no other test uses are checked.
+        worldstate_fetched("Test", Duration::from_millis(120), false);
+        message_delivered(true);
+        command_invoked("circuit find");
+        cache_size("test", 2);
+        task_ran("test");
+
+        let text = encode().unwrap();
+
+        assert!(text.contains(r#"wf_bot_worldstate_fetch_errors_total{queryable="Test"} 1"#));
+        assert!(text.contains(r#"wf_bot_worldstate_fetch_seconds_count{queryable="Test"} 1"#));
+        assert!(text.contains(r#"wf_bot_messages_total{result="sent"}"#));
+        assert!(text.contains(r#"wf_bot_commands_total{command="circuit find"} 1"#));
+        assert!(text.contains(r#"wf_bot_cache_items{cache="test"} 2"#));
+        assert!(text.contains(r#"wf_bot_task_last_run_timestamp_seconds{task="test"}"#));
+    }
+}
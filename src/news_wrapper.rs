@@ -1,20 +1,32 @@
+use crate::DateStyle;
+
+use std::borrow::Borrow;
+use std::collections::HashMap;
 use std::{hash::Hash, ops::Deref};
 
 use anyhow::Result;
-use serde::{Deserialize, Serialize, Serializer};
+use chrono::{DateTime, Utc};
+use poise::serenity_prelude::MessageId;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use warframe::worldstate::queryable;
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct News(#[serde(serialize_with = "serialize_news")] pub queryable::News);
+pub struct News(
+    #[serde(
+        serialize_with = "serialize_news",
+        deserialize_with = "deserialize_news"
+    )]
+    pub queryable::News,
+);
 
 impl News {
-    pub fn as_message(&self) -> Result<String> {
-        Ok(format!(
+    pub fn as_message(&self, style: DateStyle) -> String {
+        format!(
             "[{}] [{}]({})",
-            crate::fmt_api_date(&self.date)?,
+            crate::fmt_api_date(&self.date, style),
             self.message,
             self.link,
-        ))
+        )
     }
 }
 
@@ -46,6 +58,77 @@ impl From<queryable::News> for News {
     }
 }
 
+/// A news item that has been posted to a target, along with the ID of the message it was posted
+/// as. Equal to other posts, and to news items, with the same ID.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PostedNews {
+    pub news: News,
+    /// The ID of the posted message. `None` if the item was never actually posted, e.g., because
+    /// it was marked as seen on the first run.
+    #[serde(with = "crate::discord_id::option")]
+    pub message_id: Option<MessageId>,
+    pub expired: bool,
+}
+
+impl PostedNews {
+    pub fn new(news: News, message_id: Option<MessageId>) -> Self {
+        Self {
+            news,
+            message_id,
+            expired: false,
+        }
+    }
+
+    /// The message for the post in its current state.
+    pub fn as_message(&self) -> String {
+        let message = self.news.as_message(DateStyle::Discord);
+
+        match self.expired {
+            true => format!("{message} (expired)"),
+            false => message,
+        }
+    }
+
+    /// Returns the post as it should now look, if its message needs to be edited. That is, if the
+    /// latest version of the news item has a different message or link, or if it has expired.
+    pub fn updated(&self, latest: Option<&News>, now: DateTime<Utc>) -> Option<Self> {
+        // Posts without a message have nothing to edit.
+        self.message_id?;
+
+        let mut updated = self.clone();
+        if let Some(latest) = latest {
+            updated.news = latest.clone();
+        }
+        updated.expired = updated.news.expiry.is_some_and(|expiry| expiry <= now);
+
+        let changed = updated.news.message != self.news.message
+            || updated.news.link != self.news.link
+            || updated.expired != self.expired;
+
+        changed.then_some(updated)
+    }
+}
+
+impl PartialEq for PostedNews {
+    fn eq(&self, other: &Self) -> bool {
+        self.news == other.news
+    }
+}
+
+impl Eq for PostedNews {}
+
+impl Hash for PostedNews {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.news.hash(state);
+    }
+}
+
+impl Borrow<News> for PostedNews {
+    fn borrow(&self) -> &News {
+        &self.news
+    }
+}
+
 fn serialize_news<S>(news: &queryable::News, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
@@ -72,29 +155,106 @@ where
     state.end()
 }
 
-// TODO: see above.
-// #[cfg(test)]
-// mod news_wrapper_test {
-//     use super::*;
-//
-//     #[tokio::test]
-//     async fn serialize_is_deserialize() {
-//         use bincode::serde::{decode_from_slice, encode_to_vec};
-//         use warframe::worldstate::{Client, queryable};
-//
-//         let cfg = bincode::config::standard();
-//
-//         let most_recent_news = Client::default()
-//             .fetch::<queryable::News>()
-//             .await
-//             .unwrap()
-//             .last()
-//             .cloned()
-//             .unwrap();
-//         let most_recent_news = News(most_recent_news);
-//         let serialized = encode_to_vec(&most_recent_news, cfg).unwrap();
-//         let (deserialized, _): (News, _) = decode_from_slice(&serialized, cfg).unwrap();
-//
-//         assert_eq!(most_recent_news, deserialized)
-//     }
-// }
+/// The inverse of `serialize_news`. Fields must be read in the same order as they are written.
+fn deserialize_news<'de, D>(deserializer: D) -> Result<queryable::News, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct NewsHelper {
+        id: String,
+        message: String,
+        image_link: String,
+        priority: bool,
+        update: bool,
+        stream: bool,
+        date: DateTime<Utc>,
+        start_date: Option<DateTime<Utc>>,
+        end_date: Option<DateTime<Utc>>,
+        link: String,
+        mobile_only: bool,
+        expiry: Option<DateTime<Utc>>,
+    }
+
+    let helper = NewsHelper::deserialize(deserializer)?;
+
+    Ok(queryable::News {
+        id: helper.id,
+        message: helper.message,
+        image_link: helper.image_link,
+        priority: helper.priority,
+        update: helper.update,
+        stream: helper.stream,
+        date: helper.date,
+        start_date: helper.start_date,
+        end_date: helper.end_date,
+        // TODO: see above.
+        translations: HashMap::new(),
+        link: helper.link,
+        mobile_only: helper.mobile_only,
+        expiry: helper.expiry,
+    })
+}
+
+#[cfg(test)]
+mod news_wrapper_test {
+    use super::*;
+    use crate::test_util::news_item;
+
+    use chrono::TimeDelta;
+
+    #[test]
+    fn serialize_is_deserialize() {
+        use bincode::serde::{decode_from_slice, encode_to_vec};
+
+        let cfg = bincode::config::standard();
+
+        let news = news_item("1234");
+        let serialized = encode_to_vec(&news, cfg).unwrap();
+        let (deserialized, _): (News, _) = decode_from_slice(&serialized, cfg).unwrap();
+
+        assert_eq!(news.0, deserialized.0)
+    }
+
+    #[test]
+    fn serialize_posted_news() {
+        use bincode::serde::{decode_from_slice, encode_to_vec};
+
+        let cfg = bincode::config::standard();
+
+        let posted = PostedNews::new(news_item("1234"), Some(MessageId::new(5678)));
+        let serialized = encode_to_vec(&posted, cfg).unwrap();
+        let (deserialized, _): (PostedNews, _) = decode_from_slice(&serialized, cfg).unwrap();
+
+        assert_eq!(deserialized.message_id, posted.message_id);
+    }
+
+    #[test]
+    fn posted_news_updated() {
+        let now = Utc::now();
+        let posted = PostedNews::new(news_item("1"), Some(MessageId::new(1)));
+
+        // Nothing has changed.
+        assert_eq!(posted.updated(Some(&news_item("1")), now), None);
+        assert_eq!(posted.updated(None, now), None);
+
+        // The message was changed.
+        let mut latest = news_item("1");
+        latest.0.message = "Updated".into();
+        let updated = posted.updated(Some(&latest), now).unwrap();
+        assert_eq!(updated.news.message, "Updated");
+        assert!(!updated.expired);
+
+        // The news has expired.
+        let mut latest = news_item("1");
+        latest.0.expiry = Some(now - TimeDelta::minutes(1));
+        let updated = posted.updated(Some(&latest), now).unwrap();
+        assert!(updated.expired);
+        assert!(updated.as_message().ends_with("(expired)"));
+        assert_eq!(updated.updated(Some(&latest), now), None);
+
+        // Posts that were never sent can't be edited.
+        let unsent = PostedNews::new(latest, None);
+        assert_eq!(unsent.updated(None, now), None);
+    }
+}
@@ -0,0 +1,219 @@
+use crate::News;
+use crate::cache::{read_dump, write_dump};
+use crate::warning;
+
+use std::collections::HashSet;
+
+use anyhow::Result;
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+
+/// How many times a message is attempted before it is given up on.
+const MAX_ATTEMPTS: u32 = 12;
+
+/// The delay before the first retry. Doubles with every failed attempt.
+const BASE_DELAY: TimeDelta = TimeDelta::seconds(30);
+
+/// The longest delay between two attempts.
+const MAX_DELAY: TimeDelta = TimeDelta::hours(1);
+
+/// A message waiting to be delivered to a target.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingMessage {
+    pub id: u64,
+    /// The key of the target the message is for.
+    pub target: String,
+    pub content: String,
+    /// What the message is about, if it needs following up once delivered.
+    pub subject: Option<Subject>,
+    pub attempts: u32,
+    pub next_attempt: DateTime<Utc>,
+}
+
+/// What a message is about, for messages that need following up once delivered.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Subject {
+    /// A news item, which is marked as seen by the target once delivered.
+    News(News),
+    /// Baro Ki'Teer's visit ending at the given time. The message is edited once he leaves.
+    BaroVisit(DateTime<Utc>),
+}
+
+/// The outcome of a failed delivery.
+#[derive(Debug, PartialEq)]
+pub enum Failure {
+    /// The message will be attempted again at the given time.
+    Retrying(DateTime<Utc>),
+    /// The message has been removed from the outbox.
+    GaveUp(Box<PendingMessage>),
+}
+
+/// A persisted queue of messages that have not been delivered yet. Messages to the same target
+/// are delivered in the order they were queued.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Outbox {
+    next_id: u64,
+    pending: Vec<PendingMessage>,
+}
+
+impl Outbox {
+    /// Load the outbox from its default location, or provide an empty one.
+    pub fn new() -> Self {
+        Self::from_cache()
+            .inspect_err(|e| warning!(context = "loading outbox", "{e}"))
+            .unwrap_or_default()
+    }
+
+    /// Returns the number of messages waiting to be delivered.
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Add a message for the given target to the end of the queue.
+    pub fn push(&mut self, target: &str, content: String, subject: Option<Subject>) {
+        self.pending.push(PendingMessage {
+            id: self.next_id,
+            target: target.to_string(),
+            content,
+            subject,
+            attempts: 0,
+            next_attempt: DateTime::UNIX_EPOCH,
+        });
+        self.next_id += 1;
+    }
+
+    /// Returns `true` if a message announcing the news item is still waiting to be delivered to
+    /// the given target.
+    pub fn contains_news(&self, target: &str, news: &News) -> bool {
+        self.pending.iter().any(|message| {
+            message.target == target
+                && matches!(&message.subject, Some(Subject::News(queued)) if queued == news)
+        })
+    }
+
+    /// Returns the messages that may be attempted at the given time, in order. A message that is
+    /// not yet due holds back every later message to the same target.
+    pub fn due(&self, now: DateTime<Utc>) -> Vec<PendingMessage> {
+        let mut held_back = HashSet::new();
+
+        self.pending
+            .iter()
+            .filter(|message| {
+                if held_back.contains(&message.target) || message.next_attempt > now {
+                    held_back.insert(message.target.clone());
+                    return false;
+                }
+                true
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Remove a delivered message from the outbox.
+    pub fn mark_delivered(&mut self, id: u64) -> Option<PendingMessage> {
+        let index = self.pending.iter().position(|message| message.id == id)?;
+        Some(self.pending.remove(index))
+    }
+
+    /// Schedule a retry for a message that failed to deliver at the given time, or remove it if
+    /// it has failed too many times.
+    pub fn mark_failed(&mut self, id: u64, now: DateTime<Utc>) -> Option<Failure> {
+        let index = self.pending.iter().position(|message| message.id == id)?;
+        let message = &mut self.pending[index];
+        message.attempts += 1;
+
+        if message.attempts >= MAX_ATTEMPTS {
+            return Some(Failure::GaveUp(Box::new(self.pending.remove(index))));
+        }
+
+        message.next_attempt = now + backoff(message.attempts);
+        Some(Failure::Retrying(message.next_attempt))
+    }
+
+    /// Attempt to load a binary dump of the outbox from the default location. An outbox that has
+    /// never been dumped is empty.
+    fn from_cache() -> Result<Self> {
+        Ok(read_dump("outbox.bin")?.unwrap_or_default())
+    }
+
+    /// Dump the outbox to the default location.
+    pub fn dump(&self) -> Result<()> {
+        write_dump("outbox.bin", self)
+    }
+}
+
+/// The delay before the next attempt after the given number of failed attempts.
+fn backoff(attempts: u32) -> TimeDelta {
+    // Past this many doublings the maximum is always reached, so there is no need to compute it.
+    let doublings = attempts.saturating_sub(1).min(16);
+
+    (BASE_DELAY * 2i32.pow(doublings)).min(MAX_DELAY)
+}
+
+#[cfg(test)]
+mod outbox_test {
+    use super::*;
+    use crate::test_util::news_item;
+
+    #[test]
+    fn backoff_doubles_up_to_maximum() {
+        assert_eq!(backoff(1), TimeDelta::seconds(30));
+        assert_eq!(backoff(2), TimeDelta::seconds(60));
+        assert_eq!(backoff(3), TimeDelta::seconds(120));
+        assert_eq!(backoff(MAX_ATTEMPTS), MAX_DELAY);
+    }
+
+    #[test]
+    fn failure_holds_back_target() {
+        let now = Utc::now();
+        let mut outbox = Outbox::default();
+        outbox.push("a", "first".into(), None);
+        outbox.push("a", "second".into(), None);
+        outbox.push("b", "other".into(), None);
+
+        assert_eq!(outbox.due(now).len(), 3);
+
+        let retry_at = now + backoff(1);
+        assert_eq!(
+            outbox.mark_failed(0, now),
+            Some(Failure::Retrying(retry_at))
+        );
+
+        // The second message must wait for the first, but the other target is unaffected.
+        let due = outbox.due(now);
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].content, "other");
+
+        assert_eq!(outbox.due(retry_at).len(), 3);
+    }
+
+    #[test]
+    fn gives_up() {
+        let now = Utc::now();
+        let mut outbox = Outbox::default();
+        outbox.push("a", "message".into(), Some(Subject::News(news_item("1"))));
+
+        for _ in 1..MAX_ATTEMPTS {
+            assert!(matches!(
+                outbox.mark_failed(0, now),
+                Some(Failure::Retrying(_))
+            ));
+        }
+        assert!(matches!(
+            outbox.mark_failed(0, now),
+            Some(Failure::GaveUp(_))
+        ));
+        assert!(!outbox.contains_news("a", &news_item("1")));
+    }
+
+    #[test]
+    fn delivered_news_is_removed() {
+        let mut outbox = Outbox::default();
+        outbox.push("a", "message".into(), Some(Subject::News(news_item("1"))));
+        outbox.push("b", "message".into(), Some(Subject::News(news_item("1"))));
+
+        outbox.mark_delivered(0);
+        assert!(!outbox.contains_news("a", &news_item("1")));
+        assert!(outbox.contains_news("b", &news_item("1")));
+    }
+}
@@ -1,20 +1,104 @@
+use crate::circuit;
 use crate::handler::Handler;
+use crate::metrics;
+use crate::{error, info};
 
 use std::sync::Arc;
 
 use chrono::{DateTime, Datelike, Local, Timelike};
 use tokio::time::{Duration, sleep};
+use tracing::{Instrument, info_span};
 
 /// Spawn the periodic tasks that the bot does.
 pub async fn start_tasks(handler: Arc<Handler>) {
     // Check for news updates every minute
     let handler_clone = handler.clone();
     task(
+        "news",
         |_| true,
         move || {
             let handler = handler_clone.clone();
             async move {
-                handler.notify_news().await;
+                if let Err(e) = handler.notify_news().await {
+                    error!(context = "news task", "{e}");
+                }
+            }
+        },
+    )
+    .await;
+
+    // Keep the dashboard up to date every minute
+    let handler_clone = handler.clone();
+    task(
+        "dashboard",
+        |_| true,
+        move || {
+            let handler = handler_clone.clone();
+            async move {
+                if let Err(e) = handler.update_dashboard().await {
+                    error!(context = "dashboard task", "{e}");
+                }
+            }
+        },
+    )
+    .await;
+
+    // Keep the snapshots that commands are answered from up to date every minute
+    let handler_clone = handler.clone();
+    task(
+        "snapshots",
+        |_| true,
+        move || {
+            let handler = handler_clone.clone();
+            async move { handler.refresh_snapshots().await }
+        },
+    )
+    .await;
+
+    // Rotate the bot's presence through the current timers every minute
+    let handler_clone = handler.clone();
+    task(
+        "presence",
+        |_| true,
+        move || {
+            let handler = handler_clone.clone();
+            async move {
+                if let Err(e) = handler.update_presence().await {
+                    error!(context = "presence task", "{e}");
+                }
+            }
+        },
+    )
+    .await;
+
+    // Show the current timers in the timer channel every ten minutes, as Discord limits how often
+    // a channel can be edited
+    let handler_clone = handler.clone();
+    task(
+        "timer channel",
+        |now| now.minute() % 10 == 0,
+        move || {
+            let handler = handler_clone.clone();
+            async move {
+                if let Err(e) = handler.update_timer_channel().await {
+                    error!(context = "timer channel task", "{e}");
+                }
+            }
+        },
+    )
+    .await;
+
+    // Retry messages that could not be delivered every minute
+    let handler_clone = handler.clone();
+    task(
+        "outbox",
+        |_| true,
+        move || {
+            let handler = handler_clone.clone();
+            async move {
+                if let Err(e) = handler.flush_outbox().await {
+                    error!(context = "outbox task", "{e}");
+                }
             }
         },
     )
@@ -23,6 +107,7 @@ pub async fn start_tasks(handler: Arc<Handler>) {
     // Check for Baro Ki'Teer updates every Friday at 2pm
     let handler_clone = handler.clone();
     task(
+        "baro",
         |now| {
             let now_utc = now.with_timezone(&chrono::Utc);
             now_utc.weekday() == chrono::Weekday::Fri
@@ -35,17 +120,107 @@ pub async fn start_tasks(handler: Arc<Handler>) {
                 // Only send an update if he is in fact active.
                 // This is necessary because, unlike `Handler::notify_news`, this method *always*
                 // produces output, which is undesirable for the generic auto-check.
-                if handler.check_baro().await {
-                    handler.notify_baro().await;
+                let result = match handler.check_baro().await {
+                    Ok(true) => handler.notify_baro().await,
+                    Ok(false) => Ok(()),
+                    Err(e) => Err(e.into()),
+                };
+                if let Err(e) = result {
+                    error!(context = "baro task", "{e}");
+                }
+            }
+        },
+    )
+    .await;
+
+    // Remind the targets, and any opted in users, before Baro Ki'Teer arrives
+    let handler_clone = handler.clone();
+    task(
+        "baro arrival",
+        |_| true,
+        move || {
+            let handler = handler_clone.clone();
+            async move {
+                if let Err(e) = handler.notify_baro_arrival().await {
+                    error!(context = "baro arrival task", "{e}");
                 }
             }
         },
     )
     .await;
 
+    // Remind the targets before Baro Ki'Teer leaves, and update his posts once he has
+    let handler_clone = handler.clone();
+    task(
+        "baro departure",
+        |_| true,
+        move || {
+            let handler = handler_clone.clone();
+            async move {
+                if let Err(e) = handler.update_baro_posts().await {
+                    error!(context = "baro departure task", "{e}");
+                }
+            }
+        },
+    )
+    .await;
+
+    // Keep the guild scheduled events in line with the worldstate every 15 minutes
+    let handler_clone = handler.clone();
+    task(
+        "scheduled events",
+        |now| now.minute() % 15 == 0,
+        move || {
+            let handler = handler_clone.clone();
+            async move {
+                if let Err(e) = handler.sync_scheduled_events().await {
+                    error!(context = "scheduled events task", "{e}");
+                }
+            }
+        },
+    )
+    .await;
+
+    // Compare The Circuit's rotation with the worldstate every hour
+    let handler_clone = handler.clone();
+    task(
+        "circuit drift",
+        |now| now.minute() == 30,
+        move || {
+            let handler = handler_clone.clone();
+            async move {
+                if let Err(e) = circuit::check_drift(&handler).await {
+                    error!(context = "circuit drift task", "{e}");
+                }
+            }
+        },
+    )
+    .await;
+
+    // Reload The Circuit's rotations on SIGHUP
+    #[cfg(unix)]
+    tokio::spawn(async {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        let mut hangups = match signal(SignalKind::hangup()) {
+            Ok(hangups) => hangups,
+            Err(e) => {
+                error!(context = "circuit reload task", "{e}");
+                return;
+            }
+        };
+        while hangups.recv().await.is_some() {
+            match circuit::reload() {
+                Ok(source) => info!("reloaded the circuit rotations from {source}."),
+                Err(e) => error!(context = "circuit reload task", "{e}"),
+            }
+        }
+    });
+
     // Send an update about Weekly offerings every Monday at 0:00 UTC.
     let handler_clone = handler.clone();
     task(
+        "weekly",
         |now| {
             let now_utc = now.with_timezone(&chrono::Utc);
             now_utc.weekday() == chrono::Weekday::Mon
@@ -55,15 +230,18 @@ pub async fn start_tasks(handler: Arc<Handler>) {
         move || {
             let handler = handler_clone.clone();
             async move {
-                handler.notify_weekly().await;
+                if let Err(e) = handler.notify_weekly().await {
+                    error!(context = "weekly task", "{e}");
+                }
             }
         },
     )
     .await;
 }
 
-/// Checks every minute whether a task should be run if a time-based condition is met.
-async fn task<F, C, Fut>(mut condition: C, mut task: F)
+/// Checks every minute whether a task should be run if a time-based condition is met. The name
+/// identifies the task in the metrics.
+async fn task<F, C, Fut>(name: &'static str, mut condition: C, mut task: F)
 where
     F: FnMut() -> Fut + Send + 'static,
     C: FnMut(DateTime<Local>) -> bool + Send + 'static,
@@ -76,7 +254,8 @@ where
 
             // Determine if it is time to run the task.
             if condition(now) {
-                task().await;
+                task().instrument(info_span!("task", name)).await;
+                metrics::task_ran(name);
             }
 
             // Find the time until the start of the next minute, or simply wait a whole minut if it
@@ -0,0 +1,75 @@
+use crate::DateStyle;
+use crate::cli::{OutputFormat, PrintSubject};
+use crate::fetch::Attempts;
+use crate::handler::Handler;
+use crate::item_display::{BaroInfo, CircuitInfo, calculate_baro_string};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+/// Print the requested information to stdout.
+pub async fn print(subject: PrintSubject, format: OutputFormat) -> Result<()> {
+    let handler = Handler::new(None);
+
+    let output = match subject {
+        PrintSubject::Baro => {
+            let trader = handler
+                .trader(Attempts::Once)
+                .await
+                .context("could not fetch Baro Ki'Teer")?;
+
+            match format {
+                OutputFormat::Plain => calculate_baro_string(&trader, DateStyle::Plain)
+                    .await
+                    .join("\n"),
+                OutputFormat::Json => to_json(&BaroInfo::from(&trader))?,
+            }
+        }
+        PrintSubject::News => {
+            let news = handler
+                .recent_news(Attempts::Once)
+                .await
+                .context("could not fetch news")?;
+
+            match format {
+                OutputFormat::Plain => news
+                    .iter()
+                    .map(|news_item| news_item.as_message(DateStyle::Plain))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                OutputFormat::Json => to_json(&news)?,
+            }
+        }
+        PrintSubject::Weekly => {
+            let info = handler
+                .weekly_info(Attempts::Once)
+                .await
+                .context("could not fetch the Archon Hunt")?;
+
+            match format {
+                OutputFormat::Plain => info.as_message(),
+                OutputFormat::Json => to_json(&info)?,
+            }
+        }
+        PrintSubject::Circuit => {
+            let info = CircuitInfo::current();
+
+            match format {
+                OutputFormat::Plain => format!(
+                    "The Circuit: {}\nThe Circuit (Steel Path): {}",
+                    info.normal.join(", "),
+                    info.steel_path.join(", "),
+                ),
+                OutputFormat::Json => to_json(&info)?,
+            }
+        }
+    };
+
+    println!("{output}");
+
+    Ok(())
+}
+
+fn to_json<T: Serialize + ?Sized>(value: &T) -> Result<String> {
+    Ok(serde_json::to_string_pretty(value)?)
+}
@@ -0,0 +1,58 @@
+use crate::cli::Task;
+use crate::fetch::Attempts;
+use crate::handler::Handler;
+use crate::{error, info};
+
+use anyhow::{Result, bail};
+use itertools::Itertools;
+use warframe::worldstate::TimedEvent;
+
+/// Perform each of the given notifications once, reporting every task that failed.
+pub async fn run_once(handler: &Handler, tasks: &[Task]) -> Result<()> {
+    let mut failed = vec![];
+
+    for &task in tasks.iter().unique() {
+        let result = match task {
+            Task::News => handler.notify_news().await,
+            Task::Baro => baro(handler).await,
+            Task::Weekly => handler.notify_weekly().await,
+        };
+
+        if let Err(e) = result {
+            error!(context = format!("{task} task"), "{e}");
+            failed.push(task);
+        }
+    }
+    handler.finish_events().await;
+
+    if !failed.is_empty() {
+        bail!("tasks failed: {}", failed.iter().join(", "));
+    }
+
+    Ok(())
+}
+
+/// Perform each of the scheduled Baro Ki'Teer tasks.
+async fn baro(handler: &Handler) -> Result<()> {
+    notify_baro_if_active(handler).await?;
+    handler.notify_baro_arrival().await?;
+    handler.update_baro_posts().await
+}
+
+/// Like the scheduled Baro Ki'Teer task, this only produces output while he is active, and only
+/// once per visit.
+async fn notify_baro_if_active(handler: &Handler) -> Result<()> {
+    let trader = handler.trader(Attempts::Retrying).await?;
+
+    if !trader.active() {
+        info!("Baro Ki'Teer is not active");
+        return Ok(());
+    }
+    if handler.baro_visit_posted(&trader).await {
+        info!("Baro Ki'Teer's inventory was already posted for this visit");
+        // Deliver any posts that are still waiting to be retried.
+        return handler.flush_outbox().await;
+    }
+
+    handler.notify_baro().await
+}
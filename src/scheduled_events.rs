@@ -0,0 +1,194 @@
+use crate::cache::{read_dump, write_dump};
+use crate::next_weekly_reset;
+use crate::warning;
+
+use anyhow::Result;
+use chrono::{DateTime, TimeDelta, Utc};
+use poise::serenity_prelude::{
+    CreateScheduledEvent, EditScheduledEvent, ScheduledEventId, ScheduledEventType,
+};
+use serde::{Deserialize, Serialize};
+use warframe::worldstate::TimedEvent;
+use warframe::worldstate::queryable::VoidTrader;
+
+/// How long the weekly reset event lasts. Discord requires external events to have an end.
+const WEEKLY_RESET_LENGTH: TimeDelta = TimeDelta::hours(1);
+
+/// The details of a guild scheduled event, as shown in Discord.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventDetails {
+    pub name: String,
+    pub description: String,
+    pub location: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+/// A guild scheduled event created by the bot.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncedEvent {
+    #[serde(with = "crate::discord_id")]
+    pub id: ScheduledEventId,
+    pub details: EventDetails,
+}
+
+/// What needs doing to bring a guild scheduled event in line with the worldstate.
+#[derive(Debug, PartialEq)]
+pub enum SyncAction {
+    /// There is no event for this occurrence yet.
+    Create,
+    /// The event is for this occurrence, but its details have changed.
+    Update,
+    /// The event is up to date, or cannot be created.
+    Nothing,
+}
+
+/// The guild scheduled events the bot keeps in sync with the worldstate.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ScheduledEvents {
+    pub baro: Option<SyncedEvent>,
+    pub weekly: Option<SyncedEvent>,
+}
+
+impl EventDetails {
+    /// The event for Baro Ki'Teer's current or next visit.
+    pub fn baro(trader: &VoidTrader) -> Self {
+        Self {
+            name: "Baro Ki'Teer".to_string(),
+            description: format!("Baro Ki'Teer visits {}.", trader.location),
+            location: trader.location.clone(),
+            start: trader.activation(),
+            end: trader.expiry(),
+        }
+    }
+
+    /// The event for the next weekly reset after the given time.
+    pub fn weekly_reset(now: DateTime<Utc>) -> Self {
+        let start = next_weekly_reset(now);
+
+        Self {
+            name: "Weekly reset".to_string(),
+            description: "The Circuit and the Archon Hunt reset.".to_string(),
+            location: "Warframe".to_string(),
+            start,
+            end: start + WEEKLY_RESET_LENGTH,
+        }
+    }
+
+    /// Returns what needs doing to bring the existing event, if any, in line with these details.
+    pub fn sync_action(&self, existing: Option<&SyncedEvent>, now: DateTime<Utc>) -> SyncAction {
+        match existing {
+            Some(existing) if existing.details == *self => SyncAction::Nothing,
+            // Events that have not started yet can be moved, so the worldstate changing its mind
+            // only needs an update. Ongoing events can only be changed if they keep their start.
+            Some(existing)
+                if existing.details.start > now
+                    || (existing.details.end > now && existing.details.start == self.start) =>
+            {
+                SyncAction::Update
+            }
+            // Discord does not allow events to be created in the past.
+            _ if self.start <= now => SyncAction::Nothing,
+            _ => SyncAction::Create,
+        }
+    }
+
+    /// The builder to create an event with these details.
+    pub fn create(&self) -> CreateScheduledEvent<'_> {
+        CreateScheduledEvent::new(ScheduledEventType::External, &self.name, self.start)
+            .description(&self.description)
+            .location(&self.location)
+            .end_time(self.end)
+    }
+
+    /// The builder to change an existing event to these details at the given time.
+    pub fn edit(&self, now: DateTime<Utc>) -> EditScheduledEvent<'_> {
+        let builder = EditScheduledEvent::new()
+            .name(&self.name)
+            .description(&self.description)
+            .location(&self.location)
+            .end_time(self.end);
+
+        // Ongoing events can't be moved.
+        match self.start > now {
+            true => builder.start_time(self.start),
+            false => builder,
+        }
+    }
+}
+
+impl ScheduledEvents {
+    /// Load the events from their default location, or provide none.
+    pub fn new() -> Self {
+        read_dump("scheduled_events.bin")
+            .inspect_err(|e| warning!(context = "loading scheduled events", "{e}"))
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+    }
+
+    /// Dump the events to the default location.
+    pub fn dump(&self) -> Result<()> {
+        write_dump("scheduled_events.bin", self)
+    }
+}
+
+#[cfg(test)]
+mod scheduled_events_test {
+    use super::*;
+
+    fn details(start: DateTime<Utc>) -> EventDetails {
+        EventDetails {
+            name: "Baro Ki'Teer".into(),
+            description: String::new(),
+            location: "Strata Relay (Earth)".into(),
+            start,
+            end: start + TimeDelta::days(2),
+        }
+    }
+
+    fn synced(details: EventDetails) -> SyncedEvent {
+        SyncedEvent {
+            id: ScheduledEventId::new(1),
+            details,
+        }
+    }
+
+    #[test]
+    fn sync_action() {
+        let now = Utc::now();
+        let upcoming = details(now + TimeDelta::days(1));
+
+        assert_eq!(upcoming.sync_action(None, now), SyncAction::Create);
+        assert_eq!(
+            upcoming.sync_action(Some(&synced(upcoming.clone())), now),
+            SyncAction::Nothing
+        );
+
+        // The visit was moved before it started.
+        let moved = details(now + TimeDelta::days(2));
+        assert_eq!(
+            moved.sync_action(Some(&synced(upcoming.clone())), now),
+            SyncAction::Update
+        );
+
+        // The location of an ongoing visit changed.
+        let ongoing = details(now - TimeDelta::hours(1));
+        let mut relocated = ongoing.clone();
+        relocated.location = "Kronia Relay (Saturn)".into();
+        assert_eq!(
+            relocated.sync_action(Some(&synced(ongoing.clone())), now),
+            SyncAction::Update
+        );
+
+        // The next visit after one that has ended.
+        let ended = details(now - TimeDelta::days(3));
+        assert_eq!(
+            upcoming.sync_action(Some(&synced(ended)), now),
+            SyncAction::Create
+        );
+
+        // An ongoing visit the bot did not create an event for.
+        assert_eq!(ongoing.sync_action(None, now), SyncAction::Nothing);
+    }
+}
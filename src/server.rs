@@ -0,0 +1,221 @@
+use crate::fetch::{FetchError, Snapshot};
+use crate::handler::Handler;
+use crate::item_display::{BaroInfo, CircuitInfo};
+use crate::{metrics, warning};
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use anyhow::Result;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::http::header::{AGE, CONTENT_TYPE};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::net::TcpListener;
+
+/// How recently the worldstate must have been fetched for the bot to be ready. News is fetched
+/// every minute, so this allows for a few failures in a row.
+const READY_FETCH_AGE: TimeDelta = TimeDelta::minutes(10);
+
+/// Serve the bot's HTTP endpoints on the given address until the process exits.
+pub async fn serve(addr: SocketAddr, handler: Arc<Handler>) -> Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    axum::serve(listener, router(handler)).await?;
+
+    Ok(())
+}
+
+/// Serve the bot's metrics in the Prometheus text format on the given address until the process
+/// exits.
+pub async fn serve_metrics(addr: SocketAddr) -> Result<()> {
+    let app = Router::new().route("/metrics", get(prometheus_metrics));
+
+    let listener = TcpListener::bind(addr).await?;
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
+
+/// The bot's HTTP endpoints.
+fn router(handler: Arc<Handler>) -> Router {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .route("/calendar.ics", get(calendar))
+        .route("/api/baro", get(baro))
+        .route("/api/weekly", get(weekly))
+        .route("/api/circuit", get(circuit))
+        .route("/api/news", get(news))
+        .with_state(handler)
+}
+
+/// Whether the bot is able to do its work.
+#[derive(Debug, Serialize)]
+struct Readiness {
+    ready: bool,
+    /// `None` when delivering through webhooks, which needs no connection.
+    discord_connected: Option<bool>,
+    last_worldstate_fetch: Option<DateTime<Utc>>,
+}
+
+impl Readiness {
+    fn new(
+        discord_connected: Option<bool>,
+        last_worldstate_fetch: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let fetched_recently =
+            last_worldstate_fetch.is_some_and(|fetched| now - fetched <= READY_FETCH_AGE);
+
+        Self {
+            ready: discord_connected != Some(false) && fetched_recently,
+            discord_connected,
+            last_worldstate_fetch,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct NewsQuery {
+    count: Option<usize>,
+}
+
+/// A JSON response from a snapshot, with its `Age` in seconds, or a `502 Bad Gateway` with the
+/// reason if the worldstate has not been fetched.
+fn json_or_bad_gateway<T: Serialize>(snapshot: Result<Snapshot<T>, FetchError>) -> Response {
+    match snapshot {
+        Ok(snapshot) => {
+            let age = (Utc::now() - snapshot.fetched_at).num_seconds().max(0);
+            ([(AGE, age.to_string())], Json(snapshot.value)).into_response()
+        }
+        Err(e) => (
+            StatusCode::BAD_GATEWAY,
+            Json(serde_json::json!({ "error": format!("could not fetch the worldstate: {e}") })),
+        )
+            .into_response(),
+    }
+}
+
+async fn prometheus_metrics() -> Response {
+    match metrics::encode() {
+        Ok(text) => ([(CONTENT_TYPE, "text/plain; version=0.0.4")], text).into_response(),
+        Err(e) => {
+            warning!(context = "encoding metrics", "{e}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// The process is running.
+async fn healthz() -> &'static str {
+    "ok"
+}
+
+/// The bot is connected to Discord, if it needs to be, and has fetched the worldstate recently.
+async fn readyz(State(handler): State<Arc<Handler>>) -> Response {
+    let readiness = Readiness::new(
+        handler.discord_connected().await,
+        handler.last_fetch().await,
+        Utc::now(),
+    );
+    let status = match readiness.ready {
+        true => StatusCode::OK,
+        false => StatusCode::SERVICE_UNAVAILABLE,
+    };
+
+    (status, Json(readiness)).into_response()
+}
+
+/// An iCalendar of upcoming events, which calendar apps can subscribe to.
+async fn calendar(State(handler): State<Arc<Handler>>) -> impl IntoResponse {
+    (
+        [(CONTENT_TYPE, "text/calendar; charset=utf-8")],
+        handler.calendar().await,
+    )
+}
+
+async fn baro(State(handler): State<Arc<Handler>>) -> Response {
+    let trader = handler.latest_trader().await;
+
+    json_or_bad_gateway(trader.map(|snapshot| snapshot.map(|trader| BaroInfo::from(&trader))))
+}
+
+async fn weekly(State(handler): State<Arc<Handler>>) -> Response {
+    json_or_bad_gateway(handler.latest_weekly_info().await)
+}
+
+async fn circuit() -> Json<CircuitInfo> {
+    Json(CircuitInfo::current())
+}
+
+/// The most recent news, five items by default.
+async fn news(State(handler): State<Arc<Handler>>, Query(query): Query<NewsQuery>) -> Response {
+    let count = query.count.unwrap_or(5);
+
+    json_or_bad_gateway(handler.latest_news(count, None).await)
+}
+
+#[cfg(test)]
+mod server_test {
+    use super::*;
+    use crate::test_util::stand_in;
+
+    use warframe::worldstate::Client;
+
+    /// Serve the endpoints for a handler that fetches the worldstate from the given URL.
+    async fn serve_local(worldstate_url: &str) -> String {
+        let worldstate = Client::new(reqwest::Client::new(), worldstate_url.to_string());
+        let handler = Arc::new(Handler::new(None).with_worldstate(worldstate));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router(handler)).await });
+
+        url
+    }
+
+    #[test]
+    fn readiness() {
+        let now = Utc::now();
+
+        assert!(Readiness::new(None, Some(now), now).ready);
+        assert!(Readiness::new(Some(true), Some(now - TimeDelta::minutes(1)), now).ready);
+        assert!(!Readiness::new(Some(false), Some(now), now).ready);
+        assert!(!Readiness::new(None, None, now).ready);
+        assert!(!Readiness::new(None, Some(now - TimeDelta::hours(1)), now).ready);
+    }
+
+    #[tokio::test]
+    async fn endpoints() {
+        let (worldstate, _) =
+            stand_in(&[("200 OK", "[]"), ("500 Internal Server Error", "")]).await;
+        let url = serve_local(&worldstate).await;
+        let get = |path: &str| reqwest::get(format!("{url}{path}"));
+
+        assert_eq!(get("/healthz").await.unwrap().text().await.unwrap(), "ok");
+        assert_eq!(
+            get("/readyz").await.unwrap().status(),
+            reqwest::StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        let circuit: serde_json::Value = get("/api/circuit").await.unwrap().json().await.unwrap();
+        assert_eq!(circuit["normal"].as_array().unwrap().len(), 3);
+
+        // The stand-in serves no news, which counts as a successful fetch.
+        let news = get("/api/news").await.unwrap();
+        assert_eq!(news.status(), reqwest::StatusCode::OK);
+        assert_eq!(news.headers()["age"], "0");
+        assert_eq!(news.text().await.unwrap(), "[]");
+        assert_eq!(
+            get("/readyz").await.unwrap().status(),
+            reqwest::StatusCode::OK
+        );
+
+        let baro = get("/api/baro").await.unwrap();
+        assert_eq!(baro.status(), reqwest::StatusCode::BAD_GATEWAY);
+    }
+}
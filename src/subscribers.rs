@@ -0,0 +1,178 @@
+use crate::cache::{read_dump, write_dump};
+use crate::warning;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::Result;
+use poise::serenity_prelude::UserId;
+use serde::{Deserialize, Serialize};
+
+/// A persisted set of users that have opted in to a kind of direct message.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Subscribers {
+    #[serde(skip)]
+    name: String,
+    users: BTreeSet<u64>,
+}
+
+impl Subscribers {
+    /// Load the subscribers with the given name from their default location, or provide an empty
+    /// set.
+    pub fn new(name: &str) -> Self {
+        let mut subscribers: Self = read_dump(&Self::file_name(name))
+            .inspect_err(|e| warning!(context = "loading subscribers", "{e}"))
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        subscribers.name = name.to_string();
+
+        subscribers
+    }
+
+    /// Add a user. Returns `false` if they were already subscribed.
+    pub fn subscribe(&mut self, user: UserId) -> bool {
+        self.users.insert(user.get())
+    }
+
+    /// Remove a user. Returns `false` if they were not subscribed.
+    pub fn unsubscribe(&mut self, user: UserId) -> bool {
+        self.users.remove(&user.get())
+    }
+
+    /// Returns an iterator over the subscribed users.
+    pub fn users(&self) -> impl Iterator<Item = UserId> {
+        self.users.iter().copied().map(UserId::new)
+    }
+
+    fn file_name(name: &str) -> String {
+        format!("subscribers_{name}.bin")
+    }
+
+    /// Dump the subscribers to the default location.
+    pub fn dump(&self) -> Result<()> {
+        write_dump(&Self::file_name(&self.name), self)
+    }
+}
+
+/// The items a user wants to hear about, and how.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ItemSubscription {
+    pub items: BTreeSet<String>,
+    /// Whether to send a direct message rather than mention the user in the weekly post.
+    pub direct_message: bool,
+}
+
+/// A persisted set of users waiting for particular items to be offered, such as The Circuit's
+/// rewards.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ItemSubscribers {
+    #[serde(skip)]
+    name: String,
+    users: BTreeMap<u64, ItemSubscription>,
+}
+
+impl ItemSubscribers {
+    /// Load the subscribers with the given name from their default location, or provide an empty
+    /// set.
+    pub fn new(name: &str) -> Self {
+        let mut subscribers: Self = read_dump(&Subscribers::file_name(name))
+            .inspect_err(|e| warning!(context = "loading item subscribers", "{e}"))
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        subscribers.name = name.to_string();
+
+        subscribers
+    }
+
+    /// Subscribe a user to an item, and set how they want to be told. Returns `false` if nothing
+    /// changed.
+    pub fn subscribe(&mut self, user: UserId, item: &str, direct_message: bool) -> bool {
+        let subscription = self.users.entry(user.get()).or_default();
+        let changed = subscription.direct_message != direct_message;
+        subscription.direct_message = direct_message;
+
+        subscription.items.insert(item.to_string()) || changed
+    }
+
+    /// Unsubscribe a user from an item. Returns `false` if they were not subscribed to it.
+    pub fn unsubscribe(&mut self, user: UserId, item: &str) -> bool {
+        let Some(subscription) = self.users.get_mut(&user.get()) else {
+            return false;
+        };
+
+        let removed = subscription.items.remove(item);
+        if subscription.items.is_empty() {
+            self.users.remove(&user.get());
+        }
+
+        removed
+    }
+
+    /// The items a user is subscribed to.
+    pub fn subscription(&self, user: UserId) -> Option<&ItemSubscription> {
+        self.users.get(&user.get())
+    }
+
+    /// Returns the users subscribed to any of the offered items, with the items they are waiting
+    /// for and whether they want a direct message.
+    pub fn matching<'a>(
+        &'a self,
+        offered: &'a [&str],
+    ) -> impl Iterator<Item = (UserId, Vec<&'a str>, bool)> + 'a {
+        self.users.iter().filter_map(|(&user, subscription)| {
+            let items = offered
+                .iter()
+                .copied()
+                .filter(|item| subscription.items.contains(*item))
+                .collect::<Vec<_>>();
+
+            (!items.is_empty()).then(|| (UserId::new(user), items, subscription.direct_message))
+        })
+    }
+
+    /// Dump the subscribers to the default location.
+    pub fn dump(&self) -> Result<()> {
+        write_dump(&Subscribers::file_name(&self.name), self)
+    }
+}
+
+#[cfg(test)]
+mod subscribers_test {
+    use super::*;
+
+    #[test]
+    fn subscribe() {
+        let mut subscribers = Subscribers::default();
+
+        assert!(subscribers.subscribe(UserId::new(1)));
+        assert!(!subscribers.subscribe(UserId::new(1)));
+        assert!(subscribers.subscribe(UserId::new(2)));
+        assert_eq!(subscribers.users().count(), 2);
+
+        assert!(subscribers.unsubscribe(UserId::new(1)));
+        assert!(!subscribers.unsubscribe(UserId::new(1)));
+        assert_eq!(subscribers.users().collect::<Vec<_>>(), [UserId::new(2)]);
+    }
+
+    #[test]
+    fn item_subscribers() {
+        let mut subscribers = ItemSubscribers::default();
+        let (alice, bob) = (UserId::new(1), UserId::new(2));
+
+        assert!(subscribers.subscribe(alice, "Excalibur", false));
+        assert!(!subscribers.subscribe(alice, "Excalibur", false));
+        assert!(subscribers.subscribe(alice, "Excalibur", true));
+        assert!(subscribers.subscribe(alice, "Soma", true));
+        assert!(subscribers.subscribe(bob, "Nyx", false));
+
+        let matching = subscribers
+            .matching(&["Excalibur", "Trinity", "Soma"])
+            .collect::<Vec<_>>();
+        assert_eq!(matching, [(alice, vec!["Excalibur", "Soma"], true)]);
+
+        assert!(subscribers.unsubscribe(bob, "Nyx"));
+        assert!(!subscribers.unsubscribe(bob, "Nyx"));
+        assert_eq!(subscribers.subscription(bob), None);
+    }
+}
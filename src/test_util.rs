@@ -0,0 +1,85 @@
+use crate::News;
+
+use std::collections::HashMap;
+
+use chrono::DateTime;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use warframe::worldstate::queryable;
+
+/// A local stand-in for an HTTP service. Serves each of the given `(status, body)` responses to
+/// one request in turn, then returns the raw requests it received.
+///
+/// Returns the base URL of the stand-in, and a handle to the served requests.
+pub async fn stand_in(
+    responses: &[(&'static str, &'static str)],
+) -> (String, JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let responses = responses.to_vec();
+
+    let server = tokio::spawn(async move {
+        let mut requests = vec![];
+
+        for (status, body) in responses {
+            let (mut stream, _) = listener.accept().await.unwrap();
+
+            // Read until the end of the headers, then the rest of the body.
+            let mut request = vec![];
+            let mut buf = [0; 1024];
+            while !String::from_utf8_lossy(&request).contains("\r\n\r\n") {
+                let n = stream.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            let text = String::from_utf8_lossy(&request).to_string();
+            let (head, rest) = text.split_once("\r\n\r\n").unwrap();
+            let length: usize = head
+                .lines()
+                .find_map(|line| {
+                    line.to_lowercase()
+                        .strip_prefix("content-length: ")
+                        .map(str::to_string)
+                })
+                .and_then(|len| len.trim().parse().ok())
+                .unwrap_or(0);
+            let mut body_read = rest.len();
+            while body_read < length {
+                let n = stream.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+                body_read += n;
+            }
+
+            let response = format!(
+                "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+
+            requests.push(String::from_utf8_lossy(&request).to_string());
+        }
+
+        requests
+    });
+
+    (url, server)
+}
+
+/// A news item with the given ID, and placeholder contents.
+pub fn news_item(id: &str) -> News {
+    News(queryable::News {
+        id: id.to_string(),
+        message: format!("News {id}"),
+        image_link: String::new(),
+        priority: false,
+        update: false,
+        stream: false,
+        date: DateTime::UNIX_EPOCH,
+        start_date: None,
+        end_date: None,
+        translations: HashMap::new(),
+        link: format!("https://www.warframe.com/news/{id}"),
+        mobile_only: false,
+        expiry: None,
+    })
+}
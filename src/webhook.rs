@@ -0,0 +1,152 @@
+use std::convert::Infallible;
+use std::fmt::{self, Debug, Formatter};
+use std::str::FromStr;
+
+use anyhow::{Result, anyhow};
+use poise::serenity_prelude::MessageId;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// A Discord webhook that messages can be posted to without a bot connection.
+#[derive(Clone)]
+pub struct Webhook {
+    url: String,
+    client: reqwest::Client,
+}
+
+/// The URL contains the webhook's secret token, so only its key is shown. For the same reason,
+/// errors from posting to the webhook leave the URL out.
+impl Debug for Webhook {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Webhook").field(&self.key()).finish()
+    }
+}
+
+impl FromStr for Webhook {
+    type Err = Infallible;
+
+    fn from_str(url: &str) -> Result<Self, Self::Err> {
+        Ok(Self::new(url))
+    }
+}
+
+#[derive(Serialize)]
+struct WebhookMessage<'a> {
+    content: &'a str,
+}
+
+#[derive(Deserialize)]
+struct SentMessage {
+    id: MessageId,
+}
+
+impl Webhook {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// A stable identifier for the webhook. The URL contains a secret token, so the identifier is
+    /// a digest of it instead.
+    pub fn key(&self) -> String {
+        let digest = Sha256::digest(self.url.as_bytes());
+        format!("webhook-{}", &hex::encode(digest)[..16])
+    }
+
+    /// Post a message to the webhook, waiting for Discord to confirm that it was delivered.
+    ///
+    /// Returns the ID of the posted message.
+    pub async fn say(&self, content: &str) -> Result<MessageId> {
+        let sent: SentMessage = async {
+            self.client
+                .post(&self.url)
+                .query(&[("wait", "true")])
+                .json(&WebhookMessage { content })
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await
+        }
+        .await
+        .map_err(reqwest::Error::without_url)?;
+
+        Ok(sent.id)
+    }
+
+    /// Replace the content of a message previously posted to the webhook.
+    pub async fn edit(&self, message_id: MessageId, content: &str) -> Result<()> {
+        let mut url = reqwest::Url::parse(&self.url)?;
+        url.path_segments_mut()
+            .map_err(|_| anyhow!("invalid webhook URL"))?
+            .extend(["messages", &message_id.to_string()]);
+
+        self.client
+            .patch(url)
+            .json(&WebhookMessage { content })
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(reqwest::Error::without_url)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod webhook_test {
+    use super::*;
+    use crate::test_util::stand_in;
+
+    #[tokio::test]
+    async fn say() {
+        let (url, server) = stand_in(&[("200 OK", r#"{"id":"1234"}"#)]).await;
+
+        let webhook = Webhook::new(&format!("{url}/api/webhooks/1/token"));
+        let id = webhook.say("Hello, Tenno").await.unwrap();
+        let request = &server.await.unwrap()[0];
+
+        assert_eq!(id, MessageId::new(1234));
+        assert!(request.starts_with("POST /api/webhooks/1/token?wait=true "));
+        assert!(request.ends_with(r#"{"content":"Hello, Tenno"}"#));
+    }
+
+    #[test]
+    fn debug_hides_token() {
+        let webhook = Webhook::new("https://discord.com/api/webhooks/1/secret-token");
+        let debug = format!("{webhook:?}");
+
+        assert!(!debug.contains("secret-token"));
+        assert!(debug.contains(&webhook.key()));
+    }
+
+    #[tokio::test]
+    async fn say_error_status() {
+        let (url, server) =
+            stand_in(&[("404 Not Found", r#"{"message":"Unknown Webhook"}"#)]).await;
+
+        let webhook = Webhook::new(&format!("{url}/api/webhooks/1/secret-token"));
+        let error = webhook.say("Hello, Tenno").await.unwrap_err();
+        server.await.unwrap();
+
+        assert!(error.to_string().contains("404"));
+        assert!(!error.to_string().contains("secret-token"));
+    }
+
+    #[tokio::test]
+    async fn edit() {
+        let (url, server) = stand_in(&[("200 OK", r#"{"id":"1234"}"#)]).await;
+
+        let webhook = Webhook::new(&format!("{url}/api/webhooks/1/token"));
+        webhook
+            .edit(MessageId::new(1234), "Hello again, Tenno")
+            .await
+            .unwrap();
+        let request = &server.await.unwrap()[0];
+
+        assert!(request.starts_with("PATCH /api/webhooks/1/token/messages/1234 "));
+        assert!(request.ends_with(r#"{"content":"Hello again, Tenno"}"#));
+    }
+}